@@ -0,0 +1,14 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use zoneparser::ZoneParser;
+
+// Parse arbitrary input. The parser must return errors, never panic.
+fuzz_target!(|data: &[u8]| {
+    let mut p = ZoneParser::from_bytes(data, "fuzz.zn");
+    // Don't let the input include files from the fuzzing host
+    p.set_max_include_depth(0);
+    p.set_error_recovery(true);
+
+    for _ in p.take(10000) { }
+});
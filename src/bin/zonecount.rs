@@ -1,60 +1,141 @@
+// Explicit returns are the house style
+#![allow(clippy::needless_return)]
+
 extern crate zoneparser;
 
 use std::fs::File;
 use std::env;
+use std::io::{self, BufRead, BufReader};
 use std::process::ExitCode;
 
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 
-use zoneparser::ZoneParser;
+use zoneparser::{ZoneParser, DomainName};
 use zoneparser::RRType;
 
+// Open a zonefile for reading. The name '-' is read from stdin.
+fn open_zone(path: &str) -> io::Result<Box<dyn BufRead>> {
+    if path == "-" {
+        return Ok(Box::new(io::stdin().lock()));
+    }
+
+    return Ok(Box::new(BufReader::new(File::open(path)?)));
+}
+
 fn main() -> ExitCode {
     let args: Vec<String> = env::args().collect();
 
     let mut origin = "";
+    let mut keep_going = false;
+    let mut unicode = false;
+    let mut lint = false;
     let mut arg_count = 1;
 
-    loop {
+    while arg_count < args.len() {
         match args[arg_count].as_str() {
             "-o" | "--origin" => {
                 origin = &args[arg_count + 1];
                 arg_count += 2;
             },
+            "-k" | "--keep-going" => {
+                keep_going = true;
+                arg_count += 1;
+            },
+            "-u" | "--unicode" => {
+                unicode = true;
+                arg_count += 1;
+            },
+            "-l" | "--lint" => {
+                lint = true;
+                arg_count += 1;
+            },
             _ => break,
         }
     }
 
     if args.len() < 1 + arg_count {
-        println!("Usage: zonecount [-o origin] <zonefile>");
+        println!("Usage: zonecount [-o origin] [-k] [-u] [-l] <zonefile|->");
+        println!("The origin defaults to the name of the zone file, and is \
+                  required with stdin");
         return 10.into();
     }
 
-    if origin == "" {
+    if origin.is_empty() {
+        if args[arg_count] == "-" {
+            println!("The origin must be given with -o when reading from \
+                      stdin");
+            return 10.into();
+        }
         origin = &args[arg_count];
     }
 
-    let file = File::open(&args[arg_count]).unwrap();
+    let file = match open_zone(&args[arg_count]) {
+        Ok(f) => f,
+        Err(e) => {
+            println!("Could not open file {}: {}", &args[arg_count], e);
+            return 255.into();
+        },
+    };
 
     let mut rr_count: HashMap::<RRType, u32> = HashMap::new();
     let mut rrset_count: HashMap::<RRType, u32> = HashMap::new();
     let mut rr_total = 0;
     let mut rrset_total = 0;
+    let mut error_count = 0;
     /*
     Count sets by keeping track of last names by rrtype. So we tolerate
     different sets of different rrtypes to be mixed.
      */
-    let mut last_names: HashMap::<RRType, String> = HashMap::new();
-
-    let p = ZoneParser::new(&file, origin);
+    let mut last_names: HashMap::<RRType, DomainName> = HashMap::new();
+    // Wildcard owners, wildcards with names below them and wildcards
+    // owning NS records
+    let mut wildcards: HashSet<DomainName> = HashSet::new();
+    let mut wildcard_parents: HashSet<DomainName> = HashSet::new();
+    let mut wildcard_ns: HashSet<DomainName> = HashSet::new();
+
+    let mut p = ZoneParser::new(file, origin);
+    // Report all errors instead of stopping at the first one
+    p.set_error_recovery(keep_going);
+    // Accept names in Unicode, so that they are counted with their
+    // A-label form
+    p.set_idna(unicode);
+
+    while let Some(result) = p.next() {
+        for w in p.take_warnings() {
+            println!("Warning: {}", w);
+        }
 
-    for result in p {
         match result {
             Err(e) => {
                 println!("Parse error: {}", e);
-                return 255.into();
+                if !keep_going {
+                    return 255.into();
+                }
+                error_count += 1;
             },
             Ok(rr) => {
+                if rr.name.is_wildcard() {
+                    // Delegations can't be synthesized from a wildcard
+                    if lint && rr.rrtype == RRType::NS &&
+                        wildcard_ns.insert(rr.name.clone()) {
+                        println!("Warning: Wildcard {} owns NS records",
+                                 rr.name);
+                    }
+                    wildcards.insert(rr.name.clone());
+                }
+
+                if lint {
+                    // Names below a wildcard make it match less than
+                    // expected (RFC 4592 section 2.2.1)
+                    let mut name = rr.name.parent();
+                    while let Some(n) = name {
+                        if n.is_wildcard() {
+                            wildcard_parents.insert(n.clone());
+                        }
+                        name = n.parent();
+                    }
+                }
+
                 if let Some(last_name) = last_names.get(&rr.rrtype) {
 	            if *last_name != rr.name {
 		        if let Some(rrset_c) = rrset_count.get(&rr.rrtype) {
@@ -86,7 +167,7 @@ fn main() -> ExitCode {
 
     // Count the last rrsets
     for rrtype in last_names.keys() {
-	if let Some(rrset_c) = rrset_count.get(&rrtype) {
+	if let Some(rrset_c) = rrset_count.get(rrtype) {
 	    rrset_count.insert(*rrtype, rrset_c + 1);
 	}
 	else {
@@ -95,19 +176,34 @@ fn main() -> ExitCode {
 	rrset_total += 1;
     }
 
-    println!("");
+    println!();
     println!("RR:");
     for k in rr_count.keys() {
         println!("  {:?}: {}", k, rr_count.get(k).unwrap());
     }
     println!("  total: {}", rr_total);
 
-    println!("");
+    println!();
     println!("RRSet:");
     for k in rrset_count.keys() {
         println!("  {:?}: {}", k, rrset_count.get(k).unwrap());
     }
     println!("  total: {}", rrset_total);
 
+    println!();
+    println!("Wildcards: {}", wildcards.len());
+
+    let mut parents: Vec<&DomainName> = wildcard_parents.iter().collect();
+    parents.sort();
+    for w in parents {
+        println!("Warning: Wildcard {} has names below it", w);
+    }
+
+    if error_count > 0 {
+        println!();
+        println!("Errors: {}", error_count);
+        return 255.into();
+    }
+
     return 0.into();
 }
@@ -1,30 +1,78 @@
+// Explicit returns and field initialisers are the house style
+#![allow(clippy::needless_return, clippy::redundant_field_names)]
+
 extern crate zoneparser;
 
 use std::env;
 use std::fs::File;
+use std::io::{self, BufRead, BufReader};
 use diffs::{Diff, myers::diff};
 use std::collections::HashMap;
 use core::ops::Index;
 use std::process::ExitCode;
 
-use zoneparser::{ZoneParser, Record, RecordData, RRType};
+use zoneparser::{ZoneParser, ParseError, DomainName, Record, RData,
+                 RRType, sort_canonical};
+
+type Records = Box<dyn Iterator<Item = Result<Record, ParseError>>>;
+
+// How records are printed
+#[derive(Clone, Copy)]
+struct Format {
+    // Show TTLs with units
+    ttl_units: bool,
+    // Show owner names in Unicode
+    unicode: bool,
+}
 
 struct RecordDiffer<'a> {
     old: &'a Vec<Record>,
     new: &'a Vec<Record>,
     verbose: bool,
+    format: Format,
     has_changes: bool,
 }
 
 impl<'a> RecordDiffer<'a> {
-    fn new(old: &'a Vec<Record>, new: &'a Vec<Record>, verbose: bool) -> Self {
+    fn new(old: &'a Vec<Record>, new: &'a Vec<Record>, verbose: bool,
+           format: Format) -> Self {
         Self {
             old: old,
             new: new,
             verbose: verbose,
+            format: format,
             has_changes: false,
         }
     }
+
+    fn print_pf(&self, pf: &str, r: &Record) {
+        print_record(pf, r, self.format);
+    }
+}
+
+// Print a record with a prefix. Optionally show TTLs with units, and
+// the owner name in Unicode. The line number of the record is added as
+// a comment, if known.
+fn print_record(pf: &str, r: &Record, format: Format) {
+    let location = match r.span.as_deref() {
+        Some(s) if s.start_line == s.end_line => {
+            format!(" ; line {}", s.start_line)
+        },
+        Some(s) => format!(" ; lines {}-{}", s.start_line, s.end_line),
+        None => "".to_string(),
+    };
+
+    let mut text = match format.ttl_units {
+        true => format!("{:#}", r),
+        false => format!("{}", r),
+    };
+
+    if format.unicode {
+        let owner_len = r.name.to_string().len();
+        text.replace_range(..owner_len, &r.name.to_unicode());
+    }
+
+    println!("{} {}{}", pf, text, location);
 }
 
 impl<'a> Diff for RecordDiffer<'a> {
@@ -41,7 +89,7 @@ impl<'a> Diff for RecordDiffer<'a> {
 
         if self.verbose {
             for i in old..old + len {
-                println!("~- {}", self.old[i]);
+                self.print_pf("~-", &self.old[i]);
             }
         }
 
@@ -54,7 +102,7 @@ impl<'a> Diff for RecordDiffer<'a> {
 
         if self.verbose {
             for i in new..new + new_len {
-                println!("~+ {}", self.new[i]);
+                self.print_pf("~+", &self.new[i]);
             }
         }
 
@@ -67,11 +115,11 @@ impl<'a> Diff for RecordDiffer<'a> {
 
         if self.verbose {
             for i in old..old + old_len {
-                println!("~- {}", self.old[i]);
+                self.print_pf("~-", &self.old[i]);
             }
 
             for i in new..new + new_len {
-                println!("~+ {}", self.new[i]);
+                self.print_pf("~+", &self.new[i]);
             }
         }
 
@@ -99,7 +147,7 @@ impl RecordSet {
         self.set.push(r);
     }
 
-    fn name(&self) -> String {
+    fn name(&self) -> DomainName {
         self.set[0].name.clone()
     }
 
@@ -107,9 +155,9 @@ impl RecordSet {
         self.set[0].rrtype
     }
 
-    fn print_pf(&self, pf: &str) {
-        for i in 0..self.set.len() {
-            println!("{} {}", pf, self.set[i]);
+    fn print_pf(&self, pf: &str, format: Format) {
+        for r in &self.set {
+            print_record(pf, r, format);
         }
     }
 }
@@ -202,8 +250,17 @@ impl Diff for SetDiffer {
     }
 }
 
-struct Ring<'a> {
-    parser: ZoneParser<'a>,
+// Open a zonefile for reading. The name '-' is read from stdin.
+fn open_zone(path: &str) -> io::Result<Box<dyn BufRead>> {
+    if path == "-" {
+        return Ok(Box::new(io::stdin().lock()));
+    }
+
+    return Ok(Box::new(BufReader::new(File::open(path)?)));
+}
+
+struct Ring {
+    records: Records,
     data: Vec<RecordSet>,
     tail: usize,
     head: usize,
@@ -214,11 +271,11 @@ struct Ring<'a> {
     last: Option<RecordSet>,
 }
 
-impl<'a> Ring<'a> {
-    fn new(file: &'a File, origin: &str, buf_size: usize,
-           ignore_serial: bool, skip_dnssec: bool) -> Self {
+impl Ring {
+    fn new(records: Records, buf_size: usize, ignore_serial: bool,
+           skip_dnssec: bool) -> Self {
         Self {
-            parser: ZoneParser::new(&file, origin),
+            records: records,
             data: vec!(),
             tail: 0,
             head: 0,
@@ -230,17 +287,16 @@ impl<'a> Ring<'a> {
         }
     }
 
-    fn read_zone_records(&mut self) -> Result<(), String> {
-        let mut name = "".to_string();
+    fn read_zone_records(&mut self) -> Result<(), ParseError> {
+        let mut name = DomainName::root();
         let mut rrtype = RRType::None;
 
-        if self.last.is_some() {
-            let last = self.last.as_ref().unwrap();
+        if let Some(last) = &self.last {
             name = last.name();
             rrtype = last.rrtype();
         }
 
-        while let Some(result) = self.parser.next() {
+        while let Some(result) = self.records.next() {
             if let Err(e) = result {
                 return Err(e);
             }
@@ -253,8 +309,11 @@ impl<'a> Ring<'a> {
                 continue;
             }
 
-            if self.ignore_serial && r.rrtype == RRType::SOA {
-                r.data[2] = RecordData::new("");
+            if self.ignore_serial {
+                if let Ok(RData::SOA(mut soa)) = r.rdata() {
+                    soa.serial = 0;
+                    r.set_rdata(&RData::SOA(soa));
+                }
             }
 
             if self.last.is_some() {
@@ -315,7 +374,7 @@ impl<'a> Ring<'a> {
     }
 }
 
-impl<'a> Index<usize> for Ring<'a> {
+impl Index<usize> for Ring {
     type Output = RecordSet;
 
     fn index(&self, i: usize) -> &Self::Output {
@@ -323,23 +382,65 @@ impl<'a> Index<usize> for Ring<'a> {
     }
 }
 
-struct Differ<'a> {
-    old: Ring<'a>,
-    new: Ring<'a>,
+// Command line options
+struct Options {
+    buf_size: usize,
+    ignore_serial: bool,
+    skip_dnssec: bool,
+    verbose: bool,
+    ttl_units: bool,
+    sort: bool,
+    unicode: bool,
+}
+
+// Parse a zone. The records are optionally read into memory and sorted
+// in canonical order, so that zones in different order can be compared.
+fn zone_records(file: Box<dyn BufRead>, origin: &str, opts: &Options)
+                -> Records {
+    let mut parser = ZoneParser::new(file, origin);
+
+    // The record locations are only shown in verbose mode
+    parser.set_spans(opts.verbose);
+    // Names in Unicode are also accepted in the zones
+    parser.set_idna(opts.unicode);
+
+    if !opts.sort {
+        return Box::new(parser);
+    }
+
+    match parser.collect::<Result<Vec<Record>, ParseError>>() {
+        Ok(mut records) => {
+            sort_canonical(&mut records);
+            return Box::new(records.into_iter().map(Ok));
+        },
+        Err(e) => {
+            return Box::new(std::iter::once(Err(e)));
+        },
+    }
+}
+
+struct Differ {
+    old: Ring,
+    new: Ring,
     count: HashMap<RRType, HashMap<String, usize>>,
     verbose: bool,
+    format: Format,
 }
 
-impl<'a> Differ<'a> {
-    fn new(oldfile: &'a File, newfile: &'a File, origin: &str, buf_size: usize,
-               ignore_serial: bool, skip_dnssec: bool, verbose: bool) -> Self {
+impl Differ {
+    fn new(oldfile: Box<dyn BufRead>, newfile: Box<dyn BufRead>, origin: &str,
+           opts: &Options) -> Self {
         Self {
-            old: Ring::new(&oldfile, origin, buf_size, ignore_serial,
-                           skip_dnssec),
-            new: Ring::new(&newfile, origin, buf_size, ignore_serial,
-                           skip_dnssec),
+            old: Ring::new(zone_records(oldfile, origin, opts), opts.buf_size,
+                           opts.ignore_serial, opts.skip_dnssec),
+            new: Ring::new(zone_records(newfile, origin, opts), opts.buf_size,
+                           opts.ignore_serial, opts.skip_dnssec),
             count: HashMap::new(),
-            verbose: verbose,
+            verbose: opts.verbose,
+            format: Format {
+                ttl_units: opts.ttl_units,
+                unicode: opts.unicode,
+            },
         }
     }
 
@@ -367,7 +468,8 @@ impl<'a> Differ<'a> {
                 for i in 0..len {
                     let mut rd = RecordDiffer::new(&self.old[old + i].set,
                                                    &self.new[new + i].set,
-                                                   self.verbose);
+                                                   self.verbose,
+                                                   self.format);
 
                     diff(&mut rd,
                          &self.old[old + i].set, 0, self.old[old + i].set.len(),
@@ -383,7 +485,7 @@ impl<'a> Differ<'a> {
             DiffSection::Delete(old, len) => {
                 for i in old..old + len {
                     if self.verbose {
-                        self.old[i].print_pf("--");
+                        self.old[i].print_pf("--", self.format);
                     }
 
                     self.increment(RRType::None, "deleted");
@@ -393,7 +495,7 @@ impl<'a> Differ<'a> {
             DiffSection::Insert(new, new_len) => {
                 for i in new..new + new_len {
                     if self.verbose {
-                        self.new[i].print_pf("++");
+                        self.new[i].print_pf("++", self.format);
                     }
 
                     self.increment(RRType::None, "added");
@@ -403,7 +505,7 @@ impl<'a> Differ<'a> {
             DiffSection::Replace(old, old_len, new, new_len) => {
                 for i in old..old + old_len {
                     if self.verbose {
-                        self.old[i].print_pf("--");
+                        self.old[i].print_pf("--", self.format);
                     }
 
                     self.increment(RRType::None, "deleted");
@@ -412,7 +514,7 @@ impl<'a> Differ<'a> {
 
                 for i in new..new + new_len {
                     if self.verbose {
-                        self.new[i].print_pf("++");
+                        self.new[i].print_pf("++", self.format);
                     }
 
                     self.increment(RRType::None, "added");
@@ -426,7 +528,7 @@ impl<'a> Differ<'a> {
         let mut some_total = None;
 
         let mut types: Vec<_> = self.count.drain().collect();
-        types.sort_by(|(a, _), (b, _)| a.cmp(b));
+        types.sort_by_key(|(a, _)| *a);
 
         for (t, mut h) in types {
             if t == RRType::None {
@@ -456,15 +558,10 @@ impl<'a> Differ<'a> {
         }
     }
 
-    fn compare(&mut self) -> Result<(), String> {
+    fn compare(&mut self) -> Result<(), ParseError> {
         while !self.old.at_end && !self.new.at_end {
-            if let Err(e) = self.old.read_zone_records() {
-                return Err(e);
-            }
-
-            if let Err(e) = self.new.read_zone_records() {
-                return Err(e);
-            }
+            self.old.read_zone_records()?;
+            self.new.read_zone_records()?;
 
             let mut sd = SetDiffer::new();
 
@@ -495,34 +592,51 @@ fn main() -> ExitCode {
     let args: Vec<String> = env::args().collect();
 
     let mut origin = "";
-    let mut buf_size: usize = 1 << 16;
-    let mut verbose = false;
-    let mut ignore_serial = false;
-    let mut skip_dnssec = false;
+    let mut opts = Options {
+        buf_size: 1 << 16,
+        ignore_serial: false,
+        skip_dnssec: false,
+        verbose: false,
+        ttl_units: false,
+        sort: false,
+        unicode: false,
+    };
 
     let mut arg_count = 1;
 
-    loop {
+    while arg_count < args.len() {
         match args[arg_count].as_str() {
             "-o" | "--origin" => {
                 origin = &args[arg_count + 1];
                 arg_count += 2;
             },
             "-b" | "--buffer-size" => {
-                buf_size = args[arg_count + 1].parse().unwrap();
+                opts.buf_size = args[arg_count + 1].parse().unwrap();
                 arg_count += 2;
             }
             "-s" | "--ignore-serial" => {
                 arg_count += 1;
-                ignore_serial = true;
+                opts.ignore_serial = true;
             },
             "-d" | "--skip-dnssec" => {
                 arg_count += 1;
-                skip_dnssec = true;
+                opts.skip_dnssec = true;
             }
             "-v" | "--verbose" => {
                 arg_count += 1;
-                verbose = true;
+                opts.verbose = true;
+            },
+            "-t" | "--ttl-units" => {
+                arg_count += 1;
+                opts.ttl_units = true;
+            },
+            "-S" | "--sort" => {
+                arg_count += 1;
+                opts.sort = true;
+            },
+            "-u" | "--unicode" => {
+                arg_count += 1;
+                opts.unicode = true;
             },
             _ => break,
         }
@@ -530,21 +644,42 @@ fn main() -> ExitCode {
 
     if args.len() != arg_count + 2 {
         println!("Usage: zonediff [-o origin] [-b buffer_size] [-s] [-d] [-v]");
-        println!("    <old_zone> <new_zone>");
+        println!("    [-t] [-S] [-u] <old_zone|-> <new_zone|->");
+        println!("The origin defaults to the name of the zone file which is \
+                  not stdin");
+        return 10.into();
+    }
+
+    if args[arg_count] == "-" && args[arg_count + 1] == "-" {
+        println!("Only one of the zones can be read from stdin");
         return 10.into();
     }
 
-    if origin == "" {
-        origin = &args[arg_count];
+    // The origin is the name of the zone file, or of the other zone
+    // file when one is read from stdin
+    if origin.is_empty() {
+        origin = match args[arg_count].as_str() {
+            "-" => &args[arg_count + 1],
+            _ => &args[arg_count],
+        };
+    }
+
+    let mut files = vec!();
+
+    for path in &args[arg_count..arg_count + 2] {
+        match open_zone(path) {
+            Ok(f) => files.push(f),
+            Err(e) => {
+                println!("Could not open file {}: {}", path, e);
+                return 255.into();
+            },
+        }
     }
 
-    let oldfile = File::open(&args[arg_count]).expect(
-        &format!("Could not open file {}", &args[arg_count]));
-    let newfile = File::open(&args[arg_count + 1]).expect(
-        &format!("Could not open file {}", &args[arg_count + 1]));
+    let newfile = files.pop().unwrap();
+    let oldfile = files.pop().unwrap();
 
-    let mut differ = Differ::new(&oldfile, &newfile, origin, buf_size,
-                                 ignore_serial, skip_dnssec, verbose);
+    let mut differ = Differ::new(oldfile, newfile, origin, &opts);
     if let Err(e) = differ.compare() {
         println!("Parse error: {}", e);
         return 255.into();
@@ -0,0 +1,111 @@
+// Canonical ordering of records (RFC 4034 section 6). Signers sort the
+// zone by owner name, and the records of an RRset by their RDATA in
+// canonical wire format.
+
+use std::cmp::Ordering;
+use crate::zoneparser::{Record, RRType};
+
+impl Record {
+    // Compare records in canonical order: by owner name (RFC 4034
+    // section 6.1), class, type number and RDATA (section 6.3).
+    pub fn cmp_canonical(&self, other: &Record) -> Ordering {
+        return self.name.cmp(&other.name)
+            .then(self.class.cmp(&other.class))
+            .then(self.rrtype.discriminant()
+                  .cmp(&other.rrtype.discriminant()))
+            .then_with(|| canonical_rdata(self).cmp(&canonical_rdata(other)));
+    }
+}
+
+// Sort records in canonical order. The key of each record is computed
+// once, instead of encoding the RDATA again for every comparison.
+pub fn sort_canonical(records: &mut [Record]) {
+    records.sort_by_cached_key(|r| (r.name.clone(), r.class,
+                                    r.rrtype.discriminant(),
+                                    canonical_rdata(r)));
+}
+
+// RDATA in canonical wire format (RFC 4034 section 6.2), from the typed
+// data or the generic format. Data which can't be interpreted sorts
+// after all valid data, as a sequence of character-strings.
+fn canonical_rdata(rec: &Record) -> (bool, Vec<u8>) {
+    if let Ok(rdata) = rec.rdata() {
+        let mut wire = rdata.to_wire();
+        lowercase_names(rec.rrtype, &mut wire);
+        return (false, wire);
+    }
+
+    let mut wire = vec!();
+    for d in &rec.data {
+        push_string(&mut wire, d.as_bytes());
+    }
+
+    return (true, wire);
+}
+
+// Part of the wire format of a type
+enum Part {
+    Fixed(usize),
+    String,
+    Name,
+}
+
+// The wire format up to the last name, for the types with names in
+// lower case in canonical form: the list of RFC 4034 section 6.2,
+// without NSEC (RFC 6840 section 5.1) and the obsolete types
+fn canonical_layout(rrtype: RRType) -> &'static [Part] {
+    return match rrtype {
+        RRType::NS | RRType::CNAME | RRType::PTR | RRType::DNAME => {
+            &[Part::Name]
+        },
+        RRType::MX | RRType::KX | RRType::AFSDB => {
+            &[Part::Fixed(2), Part::Name]
+        },
+        RRType::SOA | RRType::RP => &[Part::Name, Part::Name],
+        RRType::SRV => &[Part::Fixed(6), Part::Name],
+        RRType::NAPTR => {
+            &[Part::Fixed(4), Part::String, Part::String, Part::String,
+              Part::Name]
+        },
+        RRType::RRSIG | RRType::SIG => &[Part::Fixed(18), Part::Name],
+        _ => &[],
+    };
+}
+
+// Lowercase the names in wire format data. Data in the generic format
+// is not validated, and stops the walk where it is too short.
+fn lowercase_names(rrtype: RRType, wire: &mut [u8]) {
+    let mut i = 0;
+
+    for part in canonical_layout(rrtype) {
+        match part {
+            Part::Fixed(len) => i += len,
+            Part::String => {
+                i += 1 + wire.get(i).map_or(0, |len| *len as usize);
+            },
+            Part::Name => {
+                while let Some(&len) = wire.get(i) {
+                    let end = (i + 1 + len as usize).min(wire.len());
+                    wire[i + 1..end].make_ascii_lowercase();
+                    i = end;
+
+                    if len == 0 {
+                        break;
+                    }
+                }
+            },
+        }
+    }
+}
+
+// Add a character-string, a length octet followed by the data
+fn push_string(wire: &mut Vec<u8>, data: &[u8]) {
+    for chunk in data.chunks(255) {
+        wire.push(chunk.len() as u8);
+        wire.extend_from_slice(chunk);
+    }
+
+    if data.is_empty() {
+        wire.push(0);
+    }
+}
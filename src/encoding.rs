@@ -0,0 +1,111 @@
+// Text encodings of binary record data: hex, base64 (RFC 4648 section
+// 4) and base32 with the extended hex alphabet (RFC 4648 section 7).
+
+const BASE64: &[u8; 64] =
+    b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+const BASE32HEX: &[u8; 32] = b"0123456789ABCDEFGHIJKLMNOPQRSTUV";
+
+// Decode hex digits, in upper or lower case. Signs, which
+// from_str_radix would accept, are not digits.
+pub(crate) fn decode_hex(hex: &str) -> Option<Vec<u8>> {
+    if !hex.len().is_multiple_of(2) ||
+        !hex.bytes().all(|b| b.is_ascii_hexdigit()) {
+        return None;
+    }
+
+    return (0..hex.len()).step_by(2)
+        .map(|i| u8::from_str_radix(&hex[i..i + 2], 16).ok())
+        .collect();
+}
+
+// Encode as upper case hex digits
+pub(crate) fn encode_hex(data: &[u8]) -> String {
+    return data.iter().map(|b| format!("{:02X}", b)).collect();
+}
+
+// Decode groups of bits in a text, each character giving `bits` bits
+// from the alphabet. Left over bits must be zero.
+fn decode_bits(text: &str, bits: u32, value: impl Fn(u8) -> Option<u8>)
+               -> Option<Vec<u8>> {
+    let mut data = vec!();
+    let mut acc: u32 = 0;
+    let mut acc_bits = 0;
+
+    for c in text.bytes() {
+        acc = (acc << bits) | value(c)? as u32;
+        acc_bits += bits;
+
+        if acc_bits >= 8 {
+            acc_bits -= 8;
+            data.push((acc >> acc_bits) as u8);
+            acc &= (1 << acc_bits) - 1;
+        }
+    }
+
+    if acc != 0 {
+        return None;
+    }
+
+    return Some(data);
+}
+
+fn encode_bits(data: &[u8], bits: u32, alphabet: &[u8]) -> String {
+    let mut text = String::new();
+    let mut acc: u32 = 0;
+    let mut acc_bits = 0;
+    let mask = (1 << bits) - 1;
+
+    for &b in data {
+        acc = (acc << 8) | b as u32;
+        acc_bits += 8;
+
+        while acc_bits >= bits {
+            acc_bits -= bits;
+            text.push(alphabet[((acc >> acc_bits) & mask) as usize] as char);
+        }
+    }
+
+    if acc_bits > 0 {
+        text.push(alphabet[((acc << (bits - acc_bits)) & mask) as usize]
+                  as char);
+    }
+
+    return text;
+}
+
+// Decode base64 with padding
+pub(crate) fn decode_base64(text: &str) -> Option<Vec<u8>> {
+    if !text.len().is_multiple_of(4) {
+        return None;
+    }
+
+    let unpadded = text.strip_suffix("==")
+        .or_else(|| text.strip_suffix('='))
+        .unwrap_or(text);
+
+    return decode_bits(unpadded, 6, |c| {
+        BASE64.iter().position(|&b| b == c).map(|v| v as u8)
+    });
+}
+
+pub(crate) fn encode_base64(data: &[u8]) -> String {
+    let mut text = encode_bits(data, 6, BASE64);
+
+    while !text.len().is_multiple_of(4) {
+        text.push('=');
+    }
+
+    return text;
+}
+
+// Decode base32hex without padding, in upper or lower case
+pub(crate) fn decode_base32hex(text: &str) -> Option<Vec<u8>> {
+    return decode_bits(text, 5, |c| {
+        BASE32HEX.iter().position(|&b| b == c.to_ascii_uppercase())
+            .map(|v| v as u8)
+    });
+}
+
+pub(crate) fn encode_base32hex(data: &[u8]) -> String {
+    return encode_bits(data, 5, BASE32HEX);
+}
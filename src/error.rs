@@ -0,0 +1,119 @@
+use std::fmt::{Display, Formatter};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+// Classification of parse errors
+#[non_exhaustive]
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum ErrorKind {
+    BadQuoting,
+    BadEscaping,
+    UnknownType,
+    UnknownClass,
+    // Malformed domain name
+    BadName,
+    // Relative name without a known origin
+    MissingOrigin,
+    // Name which breaks the host name rules (warning)
+    BadHostname,
+    BadTtl,
+    // No TTL in the record and no $TTL directive
+    MissingTtl,
+    UnknownDirective,
+    // Missing or malformed directive arguments
+    BadDirective,
+    UnbalancedParentheses,
+    // Include depth exceeded or include cycle
+    BadInclude,
+    Io,
+}
+
+impl Display for ErrorKind {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:?}", self)
+    }
+}
+
+// Error from the zone parser, with the position of the offending token
+#[derive(Debug, Clone)]
+pub struct ParseError {
+    pub kind: ErrorKind,
+    pub message: String,
+    // Offending token, if any
+    pub token: String,
+    // File name, if the input is a named file
+    pub file: Option<Arc<Path>>,
+    // Line number, or 0 before the first line
+    pub line: usize,
+    // Column (1-based byte position) in the line, or 0 if unknown
+    pub column: usize,
+    // Position of the $INCLUDE directives leading to the file, starting
+    // with the innermost one
+    pub included_from: Vec<(Option<PathBuf>, usize)>,
+    // The I/O error of Io errors
+    pub source: Option<Arc<std::io::Error>>,
+}
+
+impl ParseError {
+    pub fn new(kind: ErrorKind, message: &str, token: &str) -> Self {
+        Self {
+            kind: kind,
+            message: message.to_string(),
+            token: token.to_string(),
+            file: None,
+            line: 0,
+            column: 0,
+            included_from: vec!(),
+            source: None,
+        }
+    }
+}
+
+// I/O errors are compared by their kind
+impl PartialEq for ParseError {
+    fn eq(&self, other: &Self) -> bool {
+        return self.kind == other.kind && self.message == other.message &&
+            self.token == other.token && self.file == other.file &&
+            self.line == other.line && self.column == other.column &&
+            self.included_from == other.included_from &&
+            self.source.as_ref().map(|e| e.kind()) ==
+            other.source.as_ref().map(|e| e.kind());
+    }
+}
+
+impl Eq for ParseError {}
+
+impl Display for ParseError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.message)?;
+
+        if self.line > 0 {
+            write!(f, " on line {}", self.line)?;
+        }
+
+        if self.column > 0 {
+            write!(f, " column {}", self.column)?;
+        }
+
+        if let Some(file) = &self.file {
+            write!(f, " in {}", file.display())?;
+        }
+
+        for (file, line) in &self.included_from {
+            match file {
+                Some(file) => write!(f, ", included from {} line {}",
+                                     file.display(), line)?,
+                None => write!(f, ", included from line {}", line)?,
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl std::error::Error for ParseError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        return self.source.as_deref()
+            .map(|e| e as &(dyn std::error::Error + 'static));
+    }
+}
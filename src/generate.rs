@@ -0,0 +1,192 @@
+// Expansion of the BIND $GENERATE directive:
+//
+//   $GENERATE start-stop[/step] lhs [ttl] [class] type rhs
+//
+// Each iteration yields one zonefile line where the '$' modifiers in
+// the template have been replaced by the iterator value.
+
+pub(crate) struct Generate {
+    // Next value of the iterator
+    next: i64,
+    stop: i64,
+    step: i64,
+    // Template tokens following the range
+    template: Vec<String>,
+}
+
+impl Generate {
+    pub fn new(range: &str, template: Vec<String>) -> Result<Self, String> {
+        let bad_range = || format!("Bad $GENERATE range {}", range);
+
+        let (start_stop, step) = match range.split_once('/') {
+            Some((ss, st)) => (ss, st.parse::<u32>().or(Err(bad_range()))?),
+            None => (range, 1),
+        };
+
+        let (start, stop) = start_stop.split_once('-').ok_or_else(bad_range)?;
+        let start: u32 = start.parse().or(Err(bad_range()))?;
+        let stop: u32 = stop.parse().or(Err(bad_range()))?;
+
+        if start > stop || step == 0 {
+            return Err(bad_range());
+        }
+
+        return Ok(Self {
+            next: start as i64,
+            stop: stop as i64,
+            step: step as i64,
+            template: template,
+        });
+    }
+}
+
+impl Iterator for Generate {
+    type Item = Result<String, String>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.next > self.stop {
+            return None;
+        }
+
+        let value = self.next;
+        self.next += self.step;
+
+        let mut line = String::new();
+
+        for token in &self.template {
+            match substitute(token, value) {
+                Ok(s) => {
+                    line.push_str(&s);
+                    line.push(' ');
+                },
+                Err(e) => {
+                    return Some(Err(e));
+                },
+            }
+        }
+
+        line.push('\n');
+
+        return Some(Ok(line));
+    }
+}
+
+// Replace the modifiers in a template token:
+//   $                         the iterator value
+//   $$                        a literal '$'
+//   ${offset[,width[,base]]}  value + offset in the given width and base
+// Escaped characters are passed on unchanged.
+fn substitute(template: &str, value: i64) -> Result<String, String> {
+    let mut out = String::new();
+    let mut chars = template.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => {
+                out.push(c);
+                if let Some(e) = chars.next() {
+                    out.push(e);
+                }
+            },
+            '$' => {
+                match chars.peek() {
+                    Some('$') => {
+                        chars.next();
+                        out.push('$');
+                    },
+                    Some('{') => {
+                        chars.next();
+                        let mut modifier = String::new();
+                        loop {
+                            match chars.next() {
+                                Some('}') => break,
+                                Some(m) => modifier.push(m),
+                                None => {
+                                    return Err(format!(
+                                        "Bad $GENERATE modifier in {}",
+                                        template));
+                                },
+                            }
+                        }
+                        out.push_str(&format_modifier(&modifier, value)
+                                     .ok_or(format!(
+                                         "Bad $GENERATE modifier in {}",
+                                         template))?);
+                    },
+                    _ => {
+                        out.push_str(&value.to_string());
+                    },
+                }
+            },
+            _ => {
+                out.push(c);
+            },
+        }
+    }
+
+    return Ok(out);
+}
+
+// Widths beyond the length of a domain name make no sense
+const MAX_WIDTH: usize = 255;
+
+// Format the value of a ${offset[,width[,base]]} modifier
+fn format_modifier(modifier: &str, value: i64) -> Option<String> {
+    let mut fields = modifier.split(',');
+
+    let offset: i64 = fields.next()?.trim().parse().ok()?;
+    let width: usize = match fields.next() {
+        Some(w) => w.trim().parse().ok().filter(|w| *w <= MAX_WIDTH)?,
+        None => 0,
+    };
+    let base = match fields.next() {
+        Some(b) => b.trim(),
+        None => "d",
+    };
+
+    if fields.next().is_some() {
+        return None;
+    }
+
+    let v = value.checked_add(offset).filter(|v| *v >= 0)?;
+
+    return match base {
+        "d" => Some(format!("{:0w$}", v, w = width)),
+        "o" => Some(format!("{:0w$o}", v, w = width)),
+        "x" => Some(format!("{:0w$x}", v, w = width)),
+        "X" => Some(format!("{:0w$X}", v, w = width)),
+        "n" => Some(nibbles(v, width, false)),
+        "N" => Some(nibbles(v, width, true)),
+        _ => None,
+    };
+}
+
+// Reverse nibble format used for ip6.arpa names, e.g. 0x1a -> "a.1".
+// As in BIND, the width counts the output characters including the dots.
+fn nibbles(mut value: i64, mut width: usize, upper: bool) -> String {
+    let mut out = String::new();
+
+    loop {
+        let digit = format!("{:x}", value & 0x0f);
+        if upper {
+            out.push_str(&digit.to_uppercase());
+        }
+        else {
+            out.push_str(&digit);
+        }
+
+        value >>= 4;
+        width = width.saturating_sub(1);
+
+        if width > 0 || value != 0 {
+            out.push('.');
+            width = width.saturating_sub(1);
+        }
+
+        if width == 0 && value == 0 {
+            break;
+        }
+    }
+
+    return out;
+}
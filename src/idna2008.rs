@@ -0,0 +1,134 @@
+// The IDNA2008 code point rules (RFC 5892). UTS 46 allows symbols and
+// other code points which IDNA2008 disallows, so U-labels are checked
+// with the derived properties of RFC 5892 section 3, computed from the
+// Unicode data of icu_properties, and the CONTEXTO rules of appendix A.
+// The CONTEXTJ rules are checked by UTS 46 processing.
+
+use icu_properties::{CodePointMapData, CodePointSetData};
+use icu_properties::props::{GeneralCategory, Script, HangulSyllableType,
+                            ChangesWhenNfkcCasefolded,
+                            DefaultIgnorableCodePoint, WhiteSpace,
+                            NoncharacterCodePoint};
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+enum Property {
+    Pvalid,
+    ContextJ,
+    ContextO,
+    Disallowed,
+}
+
+// True if all code points of a U-label are PVALID, or CONTEXTJ or
+// CONTEXTO with their context rules met
+pub(crate) fn valid_label(label: &str) -> bool {
+    let chars: Vec<char> = label.chars().collect();
+
+    for (i, &c) in chars.iter().enumerate() {
+        let valid = match property(c) {
+            Property::Pvalid | Property::ContextJ => true,
+            Property::ContextO => context_o(&chars, i),
+            Property::Disallowed => false,
+        };
+
+        if !valid {
+            return false;
+        }
+    }
+
+    return true;
+}
+
+// The derived property of a code point (RFC 5892 section 3)
+fn property(c: char) -> Property {
+    // Exceptions (F)
+    match c {
+        '\u{00DF}' | '\u{03C2}' | '\u{06FD}' | '\u{06FE}' | '\u{0F0B}' |
+        '\u{3007}' => return Property::Pvalid,
+        '\u{00B7}' | '\u{0375}' | '\u{05F3}' | '\u{05F4}' | '\u{30FB}' |
+        '\u{0660}'..='\u{0669}' | '\u{06F0}'..='\u{06F9}' => {
+            return Property::ContextO;
+        },
+        '\u{0640}' | '\u{07FA}' | '\u{302E}' | '\u{302F}' |
+        '\u{3031}'..='\u{3035}' | '\u{303B}' => return Property::Disallowed,
+        _ => { },
+    }
+
+    let category = CodePointMapData::<GeneralCategory>::new().get(c);
+    let noncharacter =
+        CodePointSetData::new::<NoncharacterCodePoint>().contains(c);
+
+    // Unassigned (J)
+    if category == GeneralCategory::Unassigned && !noncharacter {
+        return Property::Disallowed;
+    }
+
+    // LDH (K)
+    if c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-' {
+        return Property::Pvalid;
+    }
+
+    // JoinControl (H)
+    if c == '\u{200C}' || c == '\u{200D}' {
+        return Property::ContextJ;
+    }
+
+    // Unstable (B) and IgnorableProperties (C)
+    if CodePointSetData::new::<ChangesWhenNfkcCasefolded>().contains(c) ||
+        CodePointSetData::new::<DefaultIgnorableCodePoint>().contains(c) ||
+        CodePointSetData::new::<WhiteSpace>().contains(c) || noncharacter {
+        return Property::Disallowed;
+    }
+
+    // IgnorableBlocks (D): Combining Diacritical Marks for Symbols,
+    // Musical Symbols and Ancient Greek Musical Notation
+    if matches!(c, '\u{20D0}'..='\u{20FF}' | '\u{1D100}'..='\u{1D24F}') {
+        return Property::Disallowed;
+    }
+
+    // OldHangulJamo (I)
+    let jamo = CodePointMapData::<HangulSyllableType>::new().get(c);
+    if matches!(jamo, HangulSyllableType::LeadingJamo |
+                HangulSyllableType::VowelJamo |
+                HangulSyllableType::TrailingJamo) {
+        return Property::Disallowed;
+    }
+
+    // LetterDigits (A)
+    return match category {
+        GeneralCategory::LowercaseLetter | GeneralCategory::UppercaseLetter |
+        GeneralCategory::OtherLetter | GeneralCategory::DecimalNumber |
+        GeneralCategory::ModifierLetter | GeneralCategory::NonspacingMark |
+        GeneralCategory::SpacingMark => Property::Pvalid,
+        _ => Property::Disallowed,
+    };
+}
+
+// The CONTEXTO rules (RFC 5892 appendix A.3 to A.9)
+fn context_o(chars: &[char], i: usize) -> bool {
+    let script = |c: Option<&char>| {
+        c.map(|c| CodePointMapData::<Script>::new().get(*c))
+    };
+    let before = i.checked_sub(1).and_then(|i| chars.get(i));
+    let after = chars.get(i + 1);
+
+    return match chars[i] {
+        // MIDDLE DOT, between two l
+        '\u{00B7}' => before == Some(&'l') && after == Some(&'l'),
+        // GREEK LOWER NUMERAL SIGN, before Greek
+        '\u{0375}' => script(after) == Some(Script::Greek),
+        // HEBREW PUNCTUATION GERESH and GERSHAYIM, after Hebrew
+        '\u{05F3}' | '\u{05F4}' => script(before) == Some(Script::Hebrew),
+        // KATAKANA MIDDLE DOT, with Hiragana, Katakana or Han
+        '\u{30FB}' => chars.iter().any(|c| matches!(
+            script(Some(c)),
+            Some(Script::Hiragana | Script::Katakana | Script::Han))),
+        // ARABIC-INDIC and EXTENDED ARABIC-INDIC digits are not mixed
+        '\u{0660}'..='\u{0669}' => {
+            !chars.iter().any(|c| ('\u{06F0}'..='\u{06F9}').contains(c))
+        },
+        '\u{06F0}'..='\u{06F9}' => {
+            !chars.iter().any(|c| ('\u{0660}'..='\u{0669}').contains(c))
+        },
+        _ => false,
+    };
+}
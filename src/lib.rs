@@ -1,3 +1,24 @@
+// Explicit returns and field initialisers are the house style
+#![allow(clippy::needless_return, clippy::redundant_field_names)]
+
 mod zoneparser;
+mod generate;
+mod ttl;
+mod error;
+mod name;
+mod canonical;
+mod rdata;
+mod encoding;
+mod idna2008;
 
-pub use crate::zoneparser::{ZoneParser, Record, RecordData, RRClass, RRType};
+pub use crate::zoneparser::{ZoneParser, Record, RecordData, Span, RRClass,
+                            RRType};
+pub use crate::ttl::{Ttl, TtlPolicy};
+pub use crate::error::{ErrorKind, ParseError};
+pub use crate::name::{DomainName, Labels, CasePolicy};
+pub use crate::canonical::sort_canonical;
+pub use crate::rdata::{RData, RDataError, Mx, Soa, Srv, Hinfo, Dnskey, Ds,
+                       Rrsig, Nsec, Nsec3, Nsec3Param, Loc, Caa, Tlsa,
+                       Sshfp, Cert, Svcb, SvcParam, Naptr, Uri, Rp, Afsdb,
+                       AplItem, IpseckeyGateway, Ipseckey, Hip, Csync,
+                       Zonemd, Tkey, Tsig, TypeBitmap};
@@ -0,0 +1,576 @@
+use std::cmp::Ordering;
+use std::fmt::{Debug, Display, Formatter};
+use std::hash::{Hash, Hasher};
+use std::str::FromStr;
+use idna::uts46::{Uts46, AsciiDenyList, Hyphens, DnsLength};
+use crate::idna2008;
+
+// Which domain names the parser lowercases
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq)]
+pub enum CasePolicy {
+    // Keep all names as written
+    Preserve,
+    // Lowercase owner names and $ORIGIN, keep names in the record data
+    #[default]
+    LowercaseOwners,
+    // Lowercase all names, as in the canonical form of RFC 4034
+    Lowercase,
+}
+
+impl CasePolicy {
+    pub fn owners(&self) -> bool {
+        return *self != CasePolicy::Preserve;
+    }
+
+    pub fn rdata(&self) -> bool {
+        return *self == CasePolicy::Lowercase;
+    }
+}
+
+// Maximum length of a label
+const MAX_LABEL_LEN: usize = 63;
+// Maximum length of a name in wire format, including the root label
+const MAX_NAME_LEN: usize = 255;
+
+// Domain name as a sequence of labels. The labels are kept in wire
+// format, each preceded by its length. The case of the name is kept,
+// but names compare and hash case-insensitively.
+#[derive(Clone)]
+pub struct DomainName {
+    wire: Vec<u8>,
+    // Ends with the root label, i.e. a trailing dot
+    absolute: bool,
+}
+
+impl DomainName {
+    // The root name "."
+    pub fn root() -> Self {
+        Self {
+            wire: vec!(),
+            absolute: true,
+        }
+    }
+
+    // Parse a name in presentation format, e.g. "www.example.com.".
+    // Escaped characters (\X) and decimal escapes (\DDD) are unescaped.
+    // Labels must be 1 to 63 octets, and the name at most 255 octets in
+    // wire format, counting the root label also for relative names.
+    pub fn from_bytes(name: &[u8]) -> Result<Self, String> {
+        let bad_name = |reason: &str| format!(
+            "{} in name {}", reason, String::from_utf8_lossy(name));
+
+        if name == b"." {
+            return Ok(Self::root());
+        }
+
+        let mut wire = vec!();
+        let mut label: Vec<u8> = vec!();
+        let mut absolute = false;
+        let mut i = 0;
+
+        while i < name.len() {
+            match name[i] {
+                b'\\' => {
+                    match name.get(i + 1) {
+                        Some(d) if d.is_ascii_digit() => {
+                            let value = name.get(i + 1..i + 4)
+                                .filter(|ddd| ddd.iter()
+                                        .all(|b| b.is_ascii_digit()))
+                                .and_then(|ddd| std::str::from_utf8(ddd)
+                                          .ok())
+                                .and_then(|ddd| ddd.parse::<u8>().ok())
+                                .ok_or_else(|| bad_name("Bad escaping"))?;
+                            label.push(value);
+                            i += 4;
+                        },
+                        Some(c) => {
+                            label.push(*c);
+                            i += 2;
+                        },
+                        None => {
+                            return Err(bad_name("Bad escaping"));
+                        },
+                    }
+                    continue;
+                },
+                b'.' => {
+                    if i + 1 == name.len() {
+                        absolute = true;
+                    }
+                    if label.is_empty() {
+                        return Err(bad_name("Empty label"));
+                    }
+                    push_label(&mut wire, &label)
+                        .ok_or_else(|| bad_name("Label too long"))?;
+                    label.clear();
+                },
+                b => {
+                    label.push(b);
+                },
+            }
+
+            i += 1;
+        }
+
+        if !absolute && !name.is_empty() {
+            push_label(&mut wire, &label)
+                .ok_or_else(|| bad_name("Label too long"))?;
+        }
+
+        if wire.len() + 1 > MAX_NAME_LEN {
+            return Err(bad_name("Name too long"));
+        }
+
+        return Ok(Self {
+            wire: wire,
+            absolute: absolute,
+        });
+    }
+
+    // Iterate over the labels, starting with the leftmost one. The root
+    // label is not included.
+    pub fn labels(&self) -> Labels<'_> {
+        return Labels {
+            wire: &self.wire,
+        };
+    }
+
+    // Number of labels, not counting the root label
+    pub fn label_count(&self) -> usize {
+        return self.labels().count();
+    }
+
+    pub fn is_absolute(&self) -> bool {
+        return self.absolute;
+    }
+
+    pub fn is_root(&self) -> bool {
+        return self.absolute && self.wire.is_empty();
+    }
+
+    // The name with the leftmost label removed
+    pub fn parent(&self) -> Option<DomainName> {
+        let first = self.labels().next()?;
+
+        return Some(Self {
+            wire: self.wire[first.len() + 1..].to_vec(),
+            absolute: self.absolute,
+        });
+    }
+
+    // The name with a label added to the left
+    pub fn child(&self, label: &[u8]) -> Result<DomainName, String> {
+        let bad_label = |reason: &str| format!(
+            "{} in name {}", reason, String::from_utf8_lossy(label));
+
+        if label.is_empty() {
+            return Err(bad_label("Empty label"));
+        }
+
+        let mut wire = vec!();
+        push_label(&mut wire, label)
+            .ok_or_else(|| bad_label("Label too long"))?;
+        wire.extend_from_slice(&self.wire);
+
+        let name = Self {
+            wire: wire,
+            absolute: self.absolute,
+        };
+
+        return name.check_len();
+    }
+
+    // Append a name, typically the origin to a relative name. Fails if
+    // the result is too long.
+    pub fn try_append(&self, suffix: &DomainName)
+                      -> Result<DomainName, String> {
+        return self.append(suffix).check_len();
+    }
+
+    // Append a name without checking the length of the result
+    pub fn append(&self, suffix: &DomainName) -> DomainName {
+        let mut wire = self.wire.clone();
+        wire.extend_from_slice(&suffix.wire);
+
+        return Self {
+            wire: wire,
+            absolute: suffix.absolute,
+        };
+    }
+
+    // True if the name is equal to or below the other name
+    pub fn is_subdomain_of(&self, other: &DomainName) -> bool {
+        if self.absolute != other.absolute ||
+            self.wire.len() < other.wire.len() {
+            return false;
+        }
+
+        // The other name must end on a label boundary of this name
+        let skip = self.label_count().checked_sub(other.label_count());
+
+        return match skip {
+            Some(n) => self.labels().skip(n).zip(other.labels())
+                .all(|(a, b)| a.eq_ignore_ascii_case(b)),
+            None => false,
+        };
+    }
+
+    // True if the name is directly below the other name
+    pub fn is_child_of(&self, other: &DomainName) -> bool {
+        return self.parent().is_some_and(|p| p == *other);
+    }
+
+    pub fn is_parent_of(&self, other: &DomainName) -> bool {
+        return other.is_child_of(self);
+    }
+
+    // True if the name follows the host name rules (RFC 952, RFC 1123):
+    // labels of letters, digits and hyphens, not starting or ending with
+    // a hyphen. A wildcard label "*" is allowed first.
+    pub fn is_hostname(&self) -> bool {
+        let mut labels = self.labels().peekable();
+        if labels.peek() == Some(&&b"*"[..]) {
+            labels.next();
+        }
+
+        return labels.all(is_ldh_label);
+    }
+
+    // True if the name follows the host name rules, except that labels
+    // may start with "_", as in SRV ("_sip._tcp.host"), TLSA and DKIM
+    // ("sel._domainkey.host") owner names
+    pub fn is_service_hostname(&self) -> bool {
+        return self.labels()
+            .all(|l| is_ldh_label(l.strip_prefix(b"_").unwrap_or(l)));
+    }
+
+    // Length of the name in wire format, including the root label also
+    // for relative names
+    pub fn wire_len(&self) -> usize {
+        return self.wire.len() + 1;
+    }
+
+    fn check_len(self) -> Result<DomainName, String> {
+        if self.wire_len() > MAX_NAME_LEN {
+            return Err(format!("Name too long in name {}", self));
+        }
+
+        return Ok(self);
+    }
+
+    // True if the leftmost label is "*"
+    pub fn is_wildcard(&self) -> bool {
+        return self.labels().next() == Some(&b"*"[..]);
+    }
+
+    // The name a wildcard is in, i.e. the wildcard without the "*"
+    // label. None if the name is not a wildcard.
+    pub fn wildcard_parent(&self) -> Option<DomainName> {
+        if !self.is_wildcard() {
+            return None;
+        }
+
+        return self.parent();
+    }
+
+    // True if the wildcard matches qname (RFC 4592 section 3.3.1). The
+    // qname must be below the wildcard parent, which must be its closest
+    // encloser: neither the qname nor any name between it and the parent
+    // may exist. Names exist if they own records or have names below
+    // them (empty non-terminals), as told by the exists function.
+    pub fn wildcard_matches<F>(&self, qname: &DomainName, exists: F) -> bool
+    where F: Fn(&DomainName) -> bool {
+        let parent = match self.wildcard_parent() {
+            Some(p) => p,
+            None => return false,
+        };
+
+        if !qname.is_subdomain_of(&parent) || *qname == parent {
+            return false;
+        }
+
+        let mut name = qname.clone();
+        while name != parent {
+            if exists(&name) {
+                return false;
+            }
+            name = match name.parent() {
+                Some(p) => p,
+                None => return false,
+            };
+        }
+
+        return true;
+    }
+
+    // Wire format of the name. Absolute names end with the root label.
+    pub fn to_wire(&self) -> Vec<u8> {
+        let mut wire = self.wire.clone();
+        if self.absolute {
+            wire.push(0);
+        }
+
+        return wire;
+    }
+
+    // The name with all ASCII letters in lower case
+    pub fn to_lowercase(&self) -> DomainName {
+        let mut wire = self.wire.clone();
+
+        // The length octets are below 'A', and are not changed
+        wire.make_ascii_lowercase();
+
+        return Self {
+            wire: wire,
+            absolute: self.absolute,
+        };
+    }
+
+    // The name with UTF-8 labels converted to A-labels ("xn--"), as
+    // used in the DNS, with IDNA2008 validation and without the UTS 46
+    // mapping. A-labels are validated, and all other labels are kept.
+    pub fn to_ascii(&self) -> Result<DomainName, String> {
+        let mut wire = vec!();
+
+        for label in self.labels() {
+            let ascii = label_to_ascii(label)?;
+            push_label(&mut wire, &ascii).ok_or_else(
+                || format!("Label too long in name {}",
+                           String::from_utf8_lossy(&ascii)))?;
+        }
+
+        let name = Self {
+            wire: wire,
+            absolute: self.absolute,
+        };
+
+        return name.check_len();
+    }
+
+    // The name for display, with valid A-labels shown in Unicode. Other
+    // labels are escaped as in the presentation format.
+    pub fn to_unicode(&self) -> String {
+        if self.is_root() {
+            return ".".to_string();
+        }
+
+        let mut s = String::new();
+
+        for (i, label) in self.labels().enumerate() {
+            if i > 0 {
+                s.push('.');
+            }
+
+            let unicode = match is_a_label(label) {
+                true => {
+                    let (unicode, result) = Uts46::new().to_unicode(
+                        label, AsciiDenyList::EMPTY, Hyphens::Allow);
+                    result.ok().map(|_| unicode)
+                },
+                false => None,
+            };
+
+            match unicode {
+                Some(u) => s.push_str(&u),
+                None => push_escaped(&mut s, label),
+            }
+        }
+
+        if self.absolute {
+            s.push('.');
+        }
+
+        return s;
+    }
+}
+
+// True for a letter-digit-hyphen label
+fn is_ldh_label(label: &[u8]) -> bool {
+    return label.iter().all(|b| b.is_ascii_alphanumeric() || *b == b'-') &&
+        !label.starts_with(b"-") && !label.ends_with(b"-");
+}
+
+// True for labels which are IDNA A-labels, i.e. with the "xn--" prefix
+fn is_a_label(label: &[u8]) -> bool {
+    return label.len() >= 4 && label[..4].eq_ignore_ascii_case(b"xn--");
+}
+
+// Convert a label to an A-label. U-labels, and the U-labels of
+// A-labels, are checked with UTS 46 nontransitional processing and the
+// code point rules of IDNA2008, without the UTS 46 mapping: a U-label
+// must already be in lower case and NFC, and A-labels are kept in
+// their case. Other ASCII labels are not changed, and labels which are
+// not UTF-8 are invalid.
+fn label_to_ascii(label: &[u8]) -> Result<Vec<u8>, String> {
+    if label.is_ascii() && !is_a_label(label) {
+        return Ok(label.to_vec());
+    }
+
+    let bad_idn = || format!("Invalid IDN label {}",
+                             String::from_utf8_lossy(label));
+
+    let ascii = Uts46::new().to_ascii(label, AsciiDenyList::EMPTY,
+                                      Hyphens::Check, DnsLength::Ignore)
+        .map_err(|_| bad_idn())?;
+    let (unicode, result) = Uts46::new().to_unicode(
+        ascii.as_bytes(), AsciiDenyList::EMPTY, Hyphens::Check);
+    if result.is_err() || !idna2008::valid_label(&unicode) {
+        return Err(bad_idn());
+    }
+
+    if label.is_ascii() {
+        if !ascii.as_bytes().eq_ignore_ascii_case(label) {
+            return Err(bad_idn());
+        }
+        return Ok(label.to_vec());
+    }
+
+    // The mapping changed the label if it isn't the U-label of its
+    // A-label, e.g. upper case, or U+3002 mapped to a dot
+    if unicode.as_bytes() != label {
+        return Err(bad_idn());
+    }
+
+    return Ok(ascii.into_owned().into_bytes());
+}
+
+// Add a label with its length octet
+fn push_label(wire: &mut Vec<u8>, label: &[u8]) -> Option<()> {
+    if label.len() > MAX_LABEL_LEN {
+        return None;
+    }
+
+    wire.push(label.len() as u8);
+    wire.extend_from_slice(label);
+
+    return Some(());
+}
+
+// Add a label in presentation format. Special and non-printable
+// characters are escaped.
+fn push_escaped(s: &mut String, label: &[u8]) {
+    for &b in label {
+        match b {
+            b'.' | b'\\' | b'"' | b'(' | b')' | b';' | b'@' |
+            b'$' => {
+                s.push('\\');
+                s.push(b as char);
+            },
+            0x21..=0x7e => s.push(b as char),
+            _ => s.push_str(&format!("\\{:03}", b)),
+        }
+    }
+}
+
+pub struct Labels<'a> {
+    wire: &'a [u8],
+}
+
+impl<'a> Iterator for Labels<'a> {
+    type Item = &'a [u8];
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let (len, rest) = self.wire.split_first()?;
+        let (label, rest) = rest.split_at(*len as usize);
+        self.wire = rest;
+
+        return Some(label);
+    }
+}
+
+impl PartialEq for DomainName {
+    fn eq(&self, other: &DomainName) -> bool {
+        return self.absolute == other.absolute &&
+            self.wire.eq_ignore_ascii_case(&other.wire);
+    }
+}
+
+impl Eq for DomainName {}
+
+impl Ord for DomainName {
+    // Canonical order (RFC 4034 section 6.1). The labels are compared
+    // from the right as lower case octet strings, and a name sorts before
+    // its subdomains. Relative names sort before absolute names.
+    fn cmp(&self, other: &DomainName) -> Ordering {
+        let labels: Vec<&[u8]> = self.labels().collect();
+        let other_labels: Vec<&[u8]> = other.labels().collect();
+
+        for (a, b) in labels.iter().rev().zip(other_labels.iter().rev()) {
+            let ord = a.iter().map(u8::to_ascii_lowercase)
+                .cmp(b.iter().map(u8::to_ascii_lowercase));
+            if ord != Ordering::Equal {
+                return ord;
+            }
+        }
+
+        return labels.len().cmp(&other_labels.len())
+            .then(self.absolute.cmp(&other.absolute));
+    }
+}
+
+impl PartialOrd for DomainName {
+    fn partial_cmp(&self, other: &DomainName) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl PartialEq<str> for DomainName {
+    fn eq(&self, other: &str) -> bool {
+        return other.parse::<DomainName>().is_ok_and(|n| *self == n);
+    }
+}
+
+impl PartialEq<&str> for DomainName {
+    fn eq(&self, other: &&str) -> bool {
+        return *self == **other;
+    }
+}
+
+impl Hash for DomainName {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        for b in &self.wire {
+            state.write_u8(b.to_ascii_lowercase());
+        }
+        self.absolute.hash(state);
+    }
+}
+
+impl FromStr for DomainName {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        return Self::from_bytes(s.as_bytes());
+    }
+}
+
+impl Display for DomainName {
+    // Presentation format. Special and non-printable characters are
+    // escaped.
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        if self.is_root() {
+            return write!(f, ".");
+        }
+
+        for (i, label) in self.labels().enumerate() {
+            if i > 0 {
+                write!(f, ".")?;
+            }
+
+            let mut s = String::new();
+            push_escaped(&mut s, label);
+            write!(f, "{}", s)?;
+        }
+
+        if self.absolute {
+            write!(f, ".")?;
+        }
+
+        Ok(())
+    }
+}
+
+impl Debug for DomainName {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "DomainName(\"{}\")", self)
+    }
+}
@@ -0,0 +1,2394 @@
+// Typed record data. The parser keeps the data of all types as
+// anonymous fields. Record::rdata() interprets the fields of the
+// supported types, and validates them.
+
+use std::collections::BTreeMap;
+use std::fmt::{Display, Formatter};
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+use crate::zoneparser::{Record, RecordData, RRType, rrtype_bitmap};
+use crate::name::DomainName;
+use crate::encoding::{decode_hex, encode_hex, decode_base64, encode_base64,
+                      decode_base32hex, encode_base32hex};
+
+// Maximum length of a character-string
+const MAX_STRING_LEN: usize = 255;
+
+// MX and KX
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Mx {
+    pub preference: u16,
+    pub exchange: DomainName,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Soa {
+    pub mname: DomainName,
+    pub rname: DomainName,
+    pub serial: u32,
+    pub refresh: u32,
+    pub retry: u32,
+    pub expire: u32,
+    pub minimum: u32,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Srv {
+    pub priority: u16,
+    pub weight: u16,
+    pub port: u16,
+    pub target: DomainName,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Hinfo {
+    pub cpu: Vec<u8>,
+    pub os: Vec<u8>,
+}
+
+// DNSKEY, CDNSKEY and KEY
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Dnskey {
+    pub flags: u16,
+    pub protocol: u8,
+    pub algorithm: u8,
+    pub public_key: Vec<u8>,
+}
+
+// DS, CDS, DLV and TA
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Ds {
+    pub key_tag: u16,
+    pub algorithm: u8,
+    pub digest_type: u8,
+    pub digest: Vec<u8>,
+}
+
+// RRSIG and SIG
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Rrsig {
+    pub type_covered: RRType,
+    pub algorithm: u8,
+    pub labels: u8,
+    pub original_ttl: u32,
+    // Seconds since 1970, modulo 2^32
+    pub expiration: u32,
+    pub inception: u32,
+    pub key_tag: u16,
+    pub signer: DomainName,
+    pub signature: Vec<u8>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Nsec {
+    pub next: DomainName,
+    pub types: TypeBitmap,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Nsec3 {
+    pub hash_algorithm: u8,
+    pub flags: u8,
+    pub iterations: u16,
+    pub salt: Vec<u8>,
+    pub next_hashed: Vec<u8>,
+    pub types: TypeBitmap,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Nsec3Param {
+    pub hash_algorithm: u8,
+    pub flags: u8,
+    pub iterations: u16,
+    pub salt: Vec<u8>,
+}
+
+// CAA (RFC 8659). The values of the issue, issuewild and iodef tags
+// are validated.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Caa {
+    pub flags: u8,
+    pub tag: String,
+    pub value: Vec<u8>,
+}
+
+// TLSA (RFC 6698) and SMIMEA (RFC 8162)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Tlsa {
+    pub usage: u8,
+    pub selector: u8,
+    pub matching_type: u8,
+    pub data: Vec<u8>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Sshfp {
+    pub algorithm: u8,
+    pub fingerprint_type: u8,
+    pub fingerprint: Vec<u8>,
+}
+
+// CERT (RFC 4398)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Cert {
+    pub cert_type: u16,
+    pub key_tag: u16,
+    pub algorithm: u8,
+    pub certificate: Vec<u8>,
+}
+
+// NAPTR (RFC 3403)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Naptr {
+    pub order: u16,
+    pub preference: u16,
+    pub flags: Vec<u8>,
+    pub services: Vec<u8>,
+    pub regexp: Vec<u8>,
+    pub replacement: DomainName,
+}
+
+// URI (RFC 7553)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Uri {
+    pub priority: u16,
+    pub weight: u16,
+    pub target: Vec<u8>,
+}
+
+// RP (RFC 1183)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Rp {
+    pub mbox: DomainName,
+    pub txt: DomainName,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Afsdb {
+    pub subtype: u16,
+    pub hostname: DomainName,
+}
+
+// Address prefix of an APL record (RFC 3123), with the address family
+// given by the address
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AplItem {
+    pub negation: bool,
+    pub address: IpAddr,
+    pub prefix: u8,
+}
+
+// Gateway of an IPSECKEY record, by gateway type 0 to 3
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum IpseckeyGateway {
+    None,
+    Ipv4(Ipv4Addr),
+    Ipv6(Ipv6Addr),
+    Name(DomainName),
+}
+
+// IPSECKEY (RFC 4025). The public key may be empty.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Ipseckey {
+    pub precedence: u8,
+    pub algorithm: u8,
+    pub gateway: IpseckeyGateway,
+    pub public_key: Vec<u8>,
+}
+
+// HIP (RFC 8005)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Hip {
+    pub algorithm: u8,
+    pub hit: Vec<u8>,
+    pub public_key: Vec<u8>,
+    pub rendezvous_servers: Vec<DomainName>,
+}
+
+// CSYNC (RFC 7477)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Csync {
+    pub serial: u32,
+    pub flags: u16,
+    pub types: TypeBitmap,
+}
+
+// ZONEMD (RFC 8976)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Zonemd {
+    pub serial: u32,
+    pub scheme: u8,
+    pub hash_algorithm: u8,
+    pub digest: Vec<u8>,
+}
+
+// TKEY (RFC 2930), in the presentation format of BIND: the key and
+// other data are given as a size and base64 data, which is left out
+// if the size is 0
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Tkey {
+    pub algorithm: DomainName,
+    pub inception: u32,
+    pub expiration: u32,
+    pub mode: u16,
+    pub error: u16,
+    pub key: Vec<u8>,
+    pub other: Vec<u8>,
+}
+
+// TSIG (RFC 8945), in the presentation format of BIND like TKEY. The
+// time signed has 48 bits.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Tsig {
+    pub algorithm: DomainName,
+    pub time_signed: u64,
+    pub fudge: u16,
+    pub mac: Vec<u8>,
+    pub original_id: u16,
+    pub error: u16,
+    pub other: Vec<u8>,
+}
+
+// SVCB and HTTPS record data (RFC 9460). Priority 0 is AliasMode,
+// without parameters, other priorities are ServiceMode. The parameters
+// are sorted by key.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Svcb {
+    pub priority: u16,
+    pub target: DomainName,
+    pub params: Vec<SvcParam>,
+}
+
+// Service parameter of SVCB and HTTPS records
+#[non_exhaustive]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SvcParam {
+    // Keys which the client must support
+    Mandatory(Vec<u16>),
+    // ALPN protocol identifiers
+    Alpn(Vec<Vec<u8>>),
+    NoDefaultAlpn,
+    Port(u16),
+    Ipv4Hint(Vec<Ipv4Addr>),
+    // Encrypted ClientHello configuration
+    Ech(Vec<u8>),
+    Ipv6Hint(Vec<Ipv6Addr>),
+    // DNS over HTTPS path template (RFC 9461)
+    DohPath(String),
+    // Other keys, written as keyNNNNN, with the value as octets
+    Key(u16, Vec<u8>),
+}
+
+impl SvcParam {
+    pub fn key(&self) -> u16 {
+        return match self {
+            SvcParam::Mandatory(_) => 0,
+            SvcParam::Alpn(_) => 1,
+            SvcParam::NoDefaultAlpn => 2,
+            SvcParam::Port(_) => 3,
+            SvcParam::Ipv4Hint(_) => 4,
+            SvcParam::Ech(_) => 5,
+            SvcParam::Ipv6Hint(_) => 6,
+            SvcParam::DohPath(_) => 7,
+            SvcParam::Key(key, _) => *key,
+        };
+    }
+}
+
+// LOC record data (RFC 1876), as in the wire format. Latitude and
+// longitude are in thousandths of an arc second, offset by 2^31 at the
+// equator and prime meridian, and the altitude is in centimeters above
+// a base 100000 m below the WGS 84 spheroid. The size and precisions
+// are a mantissa (high nibble) and power of ten (low nibble), in
+// centimeters.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Loc {
+    pub size: u8,
+    pub horiz_pre: u8,
+    pub vert_pre: u8,
+    pub latitude: u32,
+    pub longitude: u32,
+    pub altitude: u32,
+}
+
+// Equator and prime meridian, and the altitude base, in wire format
+const LOC_EQUATOR: i64 = 1 << 31;
+const LOC_ALTITUDE_BASE: i64 = 10_000_000;
+// Latitude and longitude limits in thousandths of an arc second
+const LOC_MAX_LATITUDE: i64 = 90 * 3_600_000;
+const LOC_MAX_LONGITUDE: i64 = 180 * 3_600_000;
+// Defaults when not given: 1 m size, 10 km and 10 m precision
+const LOC_DEFAULT_SIZE: u8 = 0x12;
+const LOC_DEFAULT_HORIZ_PRE: u8 = 0x16;
+const LOC_DEFAULT_VERT_PRE: u8 = 0x13;
+
+impl Loc {
+    // Location from decimal degrees (north and east positive) and the
+    // altitude in meters, with the default size and precisions
+    pub fn from_degrees(latitude: f64, longitude: f64, altitude: f64)
+                        -> Option<Loc> {
+        let lat = (latitude * 3_600_000.0).round();
+        let lon = (longitude * 3_600_000.0).round();
+        let alt = (altitude * 100.0).round();
+
+        if !(lat.abs() <= LOC_MAX_LATITUDE as f64 &&
+             lon.abs() <= LOC_MAX_LONGITUDE as f64 &&
+             alt >= -LOC_ALTITUDE_BASE as f64 &&
+             alt <= (u32::MAX as i64 - LOC_ALTITUDE_BASE) as f64) {
+            return None;
+        }
+
+        return Some(Loc {
+            size: LOC_DEFAULT_SIZE,
+            horiz_pre: LOC_DEFAULT_HORIZ_PRE,
+            vert_pre: LOC_DEFAULT_VERT_PRE,
+            latitude: (LOC_EQUATOR + lat as i64) as u32,
+            longitude: (LOC_EQUATOR + lon as i64) as u32,
+            altitude: (LOC_ALTITUDE_BASE + alt as i64) as u32,
+        });
+    }
+
+    pub fn latitude_degrees(&self) -> f64 {
+        return (self.latitude as i64 - LOC_EQUATOR) as f64 / 3_600_000.0;
+    }
+
+    pub fn longitude_degrees(&self) -> f64 {
+        return (self.longitude as i64 - LOC_EQUATOR) as f64 / 3_600_000.0;
+    }
+
+    pub fn altitude_meters(&self) -> f64 {
+        return (self.altitude as i64 - LOC_ALTITUDE_BASE) as f64 / 100.0;
+    }
+
+    pub fn size_meters(&self) -> f64 {
+        return decode_precision(self.size) as f64 / 100.0;
+    }
+
+    pub fn horiz_pre_meters(&self) -> f64 {
+        return decode_precision(self.horiz_pre) as f64 / 100.0;
+    }
+
+    pub fn vert_pre_meters(&self) -> f64 {
+        return decode_precision(self.vert_pre) as f64 / 100.0;
+    }
+
+    // Wire format, version 0
+    pub fn to_wire(&self) -> Vec<u8> {
+        let mut wire = vec!(0, self.size, self.horiz_pre, self.vert_pre);
+        wire.extend_from_slice(&self.latitude.to_be_bytes());
+        wire.extend_from_slice(&self.longitude.to_be_bytes());
+        wire.extend_from_slice(&self.altitude.to_be_bytes());
+
+        return wire;
+    }
+
+    // From the wire format, if it is version 0 with values in range
+    pub fn from_wire(wire: &[u8]) -> Option<Loc> {
+        if wire.len() != 16 || wire[0] != 0 {
+            return None;
+        }
+
+        let u32_at = |i: usize| u32::from_be_bytes(
+            wire[i..i + 4].try_into().unwrap());
+        let loc = Loc {
+            size: wire[1],
+            horiz_pre: wire[2],
+            vert_pre: wire[3],
+            latitude: u32_at(4),
+            longitude: u32_at(8),
+            altitude: u32_at(12),
+        };
+
+        let valid = [loc.size, loc.horiz_pre, loc.vert_pre].iter()
+            .all(|&p| p >> 4 <= 9 && p & 0xf <= 9) &&
+            (loc.latitude as i64 - LOC_EQUATOR).abs() <= LOC_MAX_LATITUDE &&
+            (loc.longitude as i64 - LOC_EQUATOR).abs() <= LOC_MAX_LONGITUDE;
+
+        return valid.then_some(loc);
+    }
+}
+
+// Type bitmap of NSEC and NSEC3 records (RFC 4034 section 4.1.2). Each
+// window block has the bits of 256 types, split in two halves, as in
+// ZoneParser::rrtype_bm_from_str.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct TypeBitmap {
+    windows: BTreeMap<u8, (u128, u128)>,
+}
+
+impl TypeBitmap {
+    pub fn new() -> Self {
+        return Default::default();
+    }
+
+    // Add a type from the window block and bitmap halves
+    pub fn insert_bitmap(&mut self, (window, bm1, bm2): (u8, u128, u128)) {
+        let w = self.windows.entry(window).or_default();
+        w.0 |= bm1;
+        w.1 |= bm2;
+    }
+
+    pub fn insert(&mut self, rrtype: RRType) {
+        let disc = rrtype.discriminant();
+        self.insert_bitmap(rrtype_bitmap(&format!("TYPE{}", disc))
+                           .unwrap_or_default());
+    }
+
+    pub fn contains(&self, rrtype: RRType) -> bool {
+        return self.types().contains(&rrtype);
+    }
+
+    // The types in the bitmap, in increasing order
+    pub fn types(&self) -> Vec<RRType> {
+        let mut types = vec!();
+
+        for (window, (bm1, bm2)) in &self.windows {
+            for bit in 0..256u16 {
+                let set = match bit {
+                    0..=127 => bm1 & (1 << (127 - bit)) != 0,
+                    _ => bm2 & (1 << (255 - bit)) != 0,
+                };
+                if set {
+                    let disc = (*window as u16) << 8 | bit;
+                    types.push(RRType::from_discriminant(disc));
+                }
+            }
+        }
+
+        return types;
+    }
+
+    // Wire format: window block number, bitmap length and the bitmap
+    // without trailing zero octets
+    pub fn to_wire(&self) -> Vec<u8> {
+        let mut wire = vec!();
+
+        for (window, (bm1, bm2)) in &self.windows {
+            let mut bitmap = bm1.to_be_bytes().to_vec();
+            bitmap.extend_from_slice(&bm2.to_be_bytes());
+            let len = bitmap.iter().rposition(|b| *b != 0)
+                .map_or(0, |i| i + 1);
+
+            if len > 0 {
+                wire.push(*window);
+                wire.push(len as u8);
+                wire.extend_from_slice(&bitmap[..len]);
+            }
+        }
+
+        return wire;
+    }
+}
+
+// Record data by type. Character-strings are kept as bytes.
+#[non_exhaustive]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RData {
+    A(Ipv4Addr),
+    AAAA(Ipv6Addr),
+    NS(DomainName),
+    CNAME(DomainName),
+    DNAME(DomainName),
+    PTR(DomainName),
+    MX(Mx),
+    SOA(Soa),
+    TXT(Vec<Vec<u8>>),
+    SPF(Vec<Vec<u8>>),
+    WALLET(Vec<Vec<u8>>),
+    SRV(Srv),
+    HINFO(Hinfo),
+    DNSKEY(Dnskey),
+    CDNSKEY(Dnskey),
+    KEY(Dnskey),
+    DS(Ds),
+    CDS(Ds),
+    DLV(Ds),
+    TA(Ds),
+    RRSIG(Rrsig),
+    SIG(Rrsig),
+    NSEC(Nsec),
+    NSEC3(Nsec3),
+    NSEC3PARAM(Nsec3Param),
+    LOC(Loc),
+    CAA(Caa),
+    TLSA(Tlsa),
+    SMIMEA(Tlsa),
+    SSHFP(Sshfp),
+    OPENPGPKEY(Vec<u8>),
+    CERT(Cert),
+    SVCB(Svcb),
+    HTTPS(Svcb),
+    RP(Rp),
+    AFSDB(Afsdb),
+    NAPTR(Naptr),
+    KX(Mx),
+    APL(Vec<AplItem>),
+    IPSECKEY(Ipseckey),
+    DHCID(Vec<u8>),
+    HIP(Hip),
+    CSYNC(Csync),
+    ZONEMD(Zonemd),
+    EUI48([u8; 6]),
+    EUI64([u8; 8]),
+    TKEY(Tkey),
+    TSIG(Tsig),
+    URI(Uri),
+    // Data in the generic format of RFC 3597 (\# length hex), for any
+    // type
+    Generic(Vec<u8>),
+}
+
+// Error from interpreting the record data
+#[non_exhaustive]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RDataError {
+    // The type has no typed representation
+    UnsupportedType(RRType),
+    // Wrong number of fields for the type
+    FieldCount(RRType, usize),
+    // Malformed field, by index
+    BadField(usize, String),
+}
+
+impl Display for RDataError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            RDataError::UnsupportedType(t) => {
+                write!(f, "No typed data for type {}", t)
+            },
+            RDataError::FieldCount(t, n) => {
+                write!(f, "Wrong number of fields ({}) for type {}", n, t)
+            },
+            RDataError::BadField(i, message) => {
+                write!(f, "Bad field {}: {}", i + 1, message)
+            },
+        }
+    }
+}
+
+impl std::error::Error for RDataError {}
+
+impl RData {
+    // Wire format of the data (RFC 1035 section 3.3 and the RFCs of the
+    // types), with names uncompressed and in their case
+    pub fn to_wire(&self) -> Vec<u8> {
+        let mut w = Wire::default();
+
+        match self {
+            RData::A(a) => w.bytes(&a.octets()),
+            RData::AAAA(a) => w.bytes(&a.octets()),
+            RData::NS(n) | RData::CNAME(n) | RData::DNAME(n) |
+            RData::PTR(n) => w.name(n),
+            RData::MX(mx) | RData::KX(mx) => {
+                w.u16(mx.preference);
+                w.name(&mx.exchange);
+            },
+            RData::SOA(soa) => {
+                w.name(&soa.mname);
+                w.name(&soa.rname);
+                for n in [soa.serial, soa.refresh, soa.retry, soa.expire,
+                          soa.minimum] {
+                    w.u32(n);
+                }
+            },
+            RData::TXT(s) | RData::SPF(s) | RData::WALLET(s) => {
+                s.iter().for_each(|s| w.string(s));
+            },
+            RData::SRV(srv) => {
+                w.u16(srv.priority);
+                w.u16(srv.weight);
+                w.u16(srv.port);
+                w.name(&srv.target);
+            },
+            RData::HINFO(h) => {
+                w.string(&h.cpu);
+                w.string(&h.os);
+            },
+            RData::DNSKEY(k) | RData::CDNSKEY(k) | RData::KEY(k) => {
+                w.u16(k.flags);
+                w.u8(k.protocol);
+                w.u8(k.algorithm);
+                w.bytes(&k.public_key);
+            },
+            RData::DS(ds) | RData::CDS(ds) | RData::DLV(ds) |
+            RData::TA(ds) => {
+                w.u16(ds.key_tag);
+                w.u8(ds.algorithm);
+                w.u8(ds.digest_type);
+                w.bytes(&ds.digest);
+            },
+            RData::RRSIG(sig) | RData::SIG(sig) => {
+                w.u16(sig.type_covered.discriminant());
+                w.u8(sig.algorithm);
+                w.u8(sig.labels);
+                w.u32(sig.original_ttl);
+                w.u32(sig.expiration);
+                w.u32(sig.inception);
+                w.u16(sig.key_tag);
+                w.name(&sig.signer);
+                w.bytes(&sig.signature);
+            },
+            RData::NSEC(nsec) => {
+                w.name(&nsec.next);
+                w.bytes(&nsec.types.to_wire());
+            },
+            RData::NSEC3(nsec3) => {
+                w.u8(nsec3.hash_algorithm);
+                w.u8(nsec3.flags);
+                w.u16(nsec3.iterations);
+                w.string(&nsec3.salt);
+                w.string(&nsec3.next_hashed);
+                w.bytes(&nsec3.types.to_wire());
+            },
+            RData::NSEC3PARAM(p) => {
+                w.u8(p.hash_algorithm);
+                w.u8(p.flags);
+                w.u16(p.iterations);
+                w.string(&p.salt);
+            },
+            RData::LOC(loc) => w.bytes(&loc.to_wire()),
+            RData::CAA(caa) => {
+                w.u8(caa.flags);
+                w.string(caa.tag.as_bytes());
+                w.bytes(&caa.value);
+            },
+            RData::TLSA(t) | RData::SMIMEA(t) => {
+                w.u8(t.usage);
+                w.u8(t.selector);
+                w.u8(t.matching_type);
+                w.bytes(&t.data);
+            },
+            RData::SSHFP(fp) => {
+                w.u8(fp.algorithm);
+                w.u8(fp.fingerprint_type);
+                w.bytes(&fp.fingerprint);
+            },
+            RData::OPENPGPKEY(key) | RData::DHCID(key) => w.bytes(key),
+            RData::CERT(cert) => {
+                w.u16(cert.cert_type);
+                w.u16(cert.key_tag);
+                w.u8(cert.algorithm);
+                w.bytes(&cert.certificate);
+            },
+            RData::SVCB(svcb) | RData::HTTPS(svcb) => {
+                w.u16(svcb.priority);
+                w.name(&svcb.target);
+                // In increasing order of keys
+                let mut params: Vec<&SvcParam> = svcb.params.iter()
+                    .collect();
+                params.sort_by_key(|p| p.key());
+                for param in params {
+                    let value = svc_param_wire(param);
+                    w.u16(param.key());
+                    w.u16(value.len() as u16);
+                    w.bytes(&value);
+                }
+            },
+            RData::RP(rp) => {
+                w.name(&rp.mbox);
+                w.name(&rp.txt);
+            },
+            RData::AFSDB(a) => {
+                w.u16(a.subtype);
+                w.name(&a.hostname);
+            },
+            RData::NAPTR(n) => {
+                w.u16(n.order);
+                w.u16(n.preference);
+                w.string(&n.flags);
+                w.string(&n.services);
+                w.string(&n.regexp);
+                w.name(&n.replacement);
+            },
+            RData::APL(items) => {
+                // Trailing zero octets of the address are left out
+                for item in items {
+                    let (family, octets) = match item.address {
+                        IpAddr::V4(a) => (1, a.octets().to_vec()),
+                        IpAddr::V6(a) => (2, a.octets().to_vec()),
+                    };
+                    let len = octets.iter().rposition(|b| *b != 0)
+                        .map_or(0, |i| i + 1);
+                    w.u16(family);
+                    w.u8(item.prefix);
+                    w.u8(len as u8 | if item.negation { 0x80 } else { 0 });
+                    w.bytes(&octets[..len]);
+                }
+            },
+            RData::IPSECKEY(key) => {
+                w.u8(key.precedence);
+                match &key.gateway {
+                    IpseckeyGateway::None => {
+                        w.u8(0);
+                        w.u8(key.algorithm);
+                    },
+                    IpseckeyGateway::Ipv4(a) => {
+                        w.u8(1);
+                        w.u8(key.algorithm);
+                        w.bytes(&a.octets());
+                    },
+                    IpseckeyGateway::Ipv6(a) => {
+                        w.u8(2);
+                        w.u8(key.algorithm);
+                        w.bytes(&a.octets());
+                    },
+                    IpseckeyGateway::Name(n) => {
+                        w.u8(3);
+                        w.u8(key.algorithm);
+                        w.name(n);
+                    },
+                }
+                w.bytes(&key.public_key);
+            },
+            RData::HIP(hip) => {
+                w.u8(hip.hit.len() as u8);
+                w.u8(hip.algorithm);
+                w.u16(hip.public_key.len() as u16);
+                w.bytes(&hip.hit);
+                w.bytes(&hip.public_key);
+                hip.rendezvous_servers.iter().for_each(|n| w.name(n));
+            },
+            RData::CSYNC(c) => {
+                w.u32(c.serial);
+                w.u16(c.flags);
+                w.bytes(&c.types.to_wire());
+            },
+            RData::ZONEMD(z) => {
+                w.u32(z.serial);
+                w.u8(z.scheme);
+                w.u8(z.hash_algorithm);
+                w.bytes(&z.digest);
+            },
+            RData::EUI48(eui) => w.bytes(eui),
+            RData::EUI64(eui) => w.bytes(eui),
+            RData::TKEY(t) => {
+                w.name(&t.algorithm);
+                w.u32(t.inception);
+                w.u32(t.expiration);
+                w.u16(t.mode);
+                w.u16(t.error);
+                w.u16(t.key.len() as u16);
+                w.bytes(&t.key);
+                w.u16(t.other.len() as u16);
+                w.bytes(&t.other);
+            },
+            RData::TSIG(t) => {
+                w.name(&t.algorithm);
+                w.bytes(&t.time_signed.to_be_bytes()[2..]);
+                w.u16(t.fudge);
+                w.u16(t.mac.len() as u16);
+                w.bytes(&t.mac);
+                w.u16(t.original_id);
+                w.u16(t.error);
+                w.u16(t.other.len() as u16);
+                w.bytes(&t.other);
+            },
+            RData::URI(uri) => {
+                w.u16(uri.priority);
+                w.u16(uri.weight);
+                w.bytes(&uri.target);
+            },
+            RData::Generic(d) => w.bytes(d),
+        }
+
+        return w.0;
+    }
+}
+
+// Builder of wire format data
+#[derive(Default)]
+struct Wire(Vec<u8>);
+
+impl Wire {
+    fn bytes(&mut self, data: &[u8]) {
+        self.0.extend_from_slice(data);
+    }
+
+    fn u8(&mut self, n: u8) {
+        self.0.push(n);
+    }
+
+    fn u16(&mut self, n: u16) {
+        self.bytes(&n.to_be_bytes());
+    }
+
+    fn u32(&mut self, n: u32) {
+        self.bytes(&n.to_be_bytes());
+    }
+
+    fn name(&mut self, name: &DomainName) {
+        self.bytes(&name.to_wire());
+    }
+
+    // Character-string: a length octet and the data
+    fn string(&mut self, data: &[u8]) {
+        self.u8(data.len() as u8);
+        self.bytes(data);
+    }
+}
+
+// Accessors for the fields of a record, with errors by field index
+struct Fields<'a> {
+    rrtype: RRType,
+    data: &'a [RecordData],
+}
+
+impl<'a> Fields<'a> {
+    // Require an exact number of fields
+    fn expect(&self, count: usize) -> Result<(), RDataError> {
+        return self.expect_range(count, count);
+    }
+
+    fn expect_range(&self, min: usize, max: usize)
+                    -> Result<(), RDataError> {
+        if self.data.len() < min || self.data.len() > max {
+            return Err(RDataError::FieldCount(self.rrtype, self.data.len()));
+        }
+
+        return Ok(());
+    }
+
+    fn text(&self, i: usize) -> Result<&'a str, RDataError> {
+        let field = self.data.get(i).ok_or(
+            RDataError::FieldCount(self.rrtype, self.data.len()))?;
+
+        return field.as_str().ok_or_else(
+            || RDataError::BadField(i, "Invalid UTF-8".to_string()));
+    }
+
+    fn parse<T: std::str::FromStr>(&self, i: usize, what: &str)
+                                   -> Result<T, RDataError> {
+        let s = self.text(i)?;
+
+        return s.parse().map_err(
+            |_| RDataError::BadField(i, format!("Bad {} {}", what, s)));
+    }
+
+    // Unsigned decimal number, without a sign
+    fn number<T: std::str::FromStr>(&self, i: usize, what: &str)
+                                    -> Result<T, RDataError> {
+        let s = self.text(i)?;
+
+        return number(s).ok_or_else(
+            || RDataError::BadField(i, format!("Bad {} {}", what, s)));
+    }
+
+    fn u8(&self, i: usize) -> Result<u8, RDataError> {
+        return self.number(i, "8-bit value");
+    }
+
+    fn u16(&self, i: usize) -> Result<u16, RDataError> {
+        return self.number(i, "16-bit value");
+    }
+
+    fn u32(&self, i: usize) -> Result<u32, RDataError> {
+        return self.number(i, "32-bit value");
+    }
+
+    fn name(&self, i: usize) -> Result<DomainName, RDataError> {
+        return self.text(i)?.parse().map_err(|e| RDataError::BadField(i, e));
+    }
+
+    fn string(&self, i: usize) -> Result<Vec<u8>, RDataError> {
+        let field = self.data.get(i).ok_or(
+            RDataError::FieldCount(self.rrtype, self.data.len()))?;
+
+        if field.as_bytes().len() > MAX_STRING_LEN {
+            return Err(RDataError::BadField(
+                i, "Character-string too long".to_string()));
+        }
+
+        return Ok(field.as_bytes().to_vec());
+    }
+
+    // All fields as character-strings, at least one
+    fn strings(&self) -> Result<Vec<Vec<u8>>, RDataError> {
+        self.expect_range(1, usize::MAX)?;
+
+        return (0..self.data.len()).map(|i| self.string(i)).collect();
+    }
+
+    // The remaining fields from i joined, for base64 and hex data which
+    // may be split in several fields
+    fn joined(&self, i: usize) -> Result<String, RDataError> {
+        self.expect_range(i + 1, usize::MAX)?;
+
+        let mut text = String::new();
+        for j in i..self.data.len() {
+            text.push_str(self.text(j)?);
+        }
+
+        return Ok(text);
+    }
+
+    fn base64(&self, i: usize) -> Result<Vec<u8>, RDataError> {
+        let text = self.joined(i)?;
+
+        return decode_base64(&text).ok_or_else(
+            || RDataError::BadField(i, format!("Bad base64 data {}", text)));
+    }
+
+    fn hex(&self, i: usize) -> Result<Vec<u8>, RDataError> {
+        let text = self.joined(i)?;
+
+        return decode_hex(&text).ok_or_else(
+            || RDataError::BadField(i, format!("Bad hex data {}", text)));
+    }
+
+    // Hex data in a single field, where "-" is empty
+    fn salt(&self, i: usize) -> Result<Vec<u8>, RDataError> {
+        return match self.text(i)? {
+            "-" => Ok(vec!()),
+            salt => decode_hex(salt).ok_or_else(
+                || RDataError::BadField(i, format!("Bad salt {}", salt))),
+        };
+    }
+
+    // Number, or mnemonic from a table
+    fn mnemonic<T: std::str::FromStr + Copy>(&self, i: usize,
+                                             table: &[(T, &str)],
+                                             what: &str)
+                                             -> Result<T, RDataError> {
+        let s = self.text(i)?;
+
+        if let Some(n) = number(s) {
+            return Ok(n);
+        }
+
+        return table.iter().find(|(_, m)| m.eq_ignore_ascii_case(s))
+            .map(|(n, _)| *n).ok_or_else(
+                || RDataError::BadField(i, format!("Bad {} {}", what, s)));
+    }
+
+    // DNSSEC algorithm number or mnemonic
+    fn algorithm(&self, i: usize) -> Result<u8, RDataError> {
+        return self.mnemonic(i, &ALGORITHMS, "algorithm");
+    }
+
+    // Digest from field i, with the length for the digest type if known
+    fn digest(&self, i: usize, digest_type: u8, lengths: &[(u8, usize)])
+              -> Result<Vec<u8>, RDataError> {
+        let digest = self.hex(i)?;
+
+        match lengths.iter().find(|(t, _)| *t == digest_type) {
+            Some((_, len)) if digest.len() != *len => {
+                Err(RDataError::BadField(i, format!(
+                    "Digest length {} does not match type {} ({})",
+                    digest.len(), digest_type, len)))
+            },
+            _ => Ok(digest),
+        }
+    }
+
+    // Size in field *i, and base64 data of that size in the next field,
+    // which is left out if the size is 0. Moves i past them.
+    fn sized_base64(&self, i: &mut usize) -> Result<Vec<u8>, RDataError> {
+        let size = self.u16(*i)? as usize;
+        *i += 1;
+
+        if size == 0 {
+            return Ok(vec!());
+        }
+
+        let text = self.text(*i)?;
+        let data = decode_base64(text).ok_or_else(
+            || RDataError::BadField(*i, format!("Bad base64 data {}",
+                                                text)))?;
+        if data.len() != size {
+            return Err(RDataError::BadField(*i, format!(
+                "Length {} does not match size {}", data.len(), size)));
+        }
+        *i += 1;
+
+        return Ok(data);
+    }
+
+    // EUI-48 or EUI-64 address as hex pairs separated by "-"
+    fn eui<const N: usize>(&self, i: usize) -> Result<[u8; N], RDataError> {
+        let s = self.text(i)?;
+        let bad = || RDataError::BadField(i, format!("Bad address {}", s));
+        let mut eui = [0; N];
+
+        let parts = s.split('-').collect::<Vec<_>>();
+        if parts.len() != N {
+            return Err(bad());
+        }
+        for (n, part) in parts.iter().enumerate() {
+            match decode_hex(part).as_deref() {
+                Some(&[b]) => eui[n] = b,
+                _ => {
+                    return Err(bad());
+                },
+            }
+        }
+
+        return Ok(eui);
+    }
+
+    // APL item: an optional "!", the address family, ":", the address,
+    // "/" and the prefix length
+    fn apl_item(&self, i: usize) -> Result<AplItem, RDataError> {
+        let s = self.text(i)?;
+        let bad = |what: &str| RDataError::BadField(
+            i, format!("Bad {} in {}", what, s));
+
+        let (negation, item) = match s.strip_prefix('!') {
+            Some(item) => (true, item),
+            None => (false, s),
+        };
+        let (family, item) = item.split_once(':')
+            .ok_or_else(|| bad("address family"))?;
+        let (address, prefix) = item.split_once('/')
+            .ok_or_else(|| bad("prefix"))?;
+        let prefix: u8 = number(prefix).ok_or_else(|| bad("prefix"))?;
+
+        let (address, max) = match family {
+            "1" => (address.parse().map(IpAddr::V4), 32),
+            "2" => (address.parse().map(IpAddr::V6), 128),
+            _ => {
+                return Err(bad("address family"));
+            },
+        };
+        let address = address.map_err(|_| bad("address"))?;
+        if prefix > max {
+            return Err(bad("prefix"));
+        }
+
+        return Ok(AplItem {
+            negation: negation,
+            address: address,
+            prefix: prefix,
+        });
+    }
+
+    fn rrtype(&self, i: usize) -> Result<RRType, RDataError> {
+        let s = self.text(i)?;
+        let mut bitmap = TypeBitmap::new();
+        bitmap.insert_bitmap(rrtype_bitmap(s).ok_or_else(
+            || RDataError::BadField(i, format!("Unknown type {}", s)))?);
+
+        return Ok(bitmap.types()[0]);
+    }
+
+    // The remaining fields from i as a type bitmap
+    fn types(&self, i: usize) -> Result<TypeBitmap, RDataError> {
+        let mut bitmap = TypeBitmap::new();
+
+        for j in i..self.data.len() {
+            let s = self.text(j)?;
+            bitmap.insert_bitmap(rrtype_bitmap(s).ok_or_else(
+                || RDataError::BadField(j, format!("Unknown type {}", s)))?);
+        }
+
+        return Ok(bitmap);
+    }
+
+    // RRSIG time, either YYYYMMDDHHmmSS or seconds since 1970
+    fn time(&self, i: usize) -> Result<u32, RDataError> {
+        let s = self.text(i)?;
+        let bad_time = || RDataError::BadField(i, format!("Bad time {}", s));
+
+        if s.len() != 14 {
+            return number(s).ok_or_else(bad_time);
+        }
+
+        let num = |range: std::ops::Range<usize>| -> Result<i64, RDataError> {
+            return s.get(range).and_then(number).ok_or_else(bad_time);
+        };
+        let (year, month, day) = (num(0..4)?, num(4..6)?, num(6..8)?);
+        let (hour, min, sec) = (num(8..10)?, num(10..12)?, num(12..14)?);
+
+        if !(1..=12).contains(&month) || day < 1 ||
+            day > days_in_month(year, month) || hour > 23 || min > 59 ||
+            sec > 59 {
+            return Err(bad_time());
+        }
+
+        let t = days_from_civil(year, month, day) * 86400 + hour * 3600 +
+            min * 60 + sec;
+
+        // Serial number arithmetic (RFC 4034 section 3.1.5)
+        return Ok(t as u32);
+    }
+
+    // LOC data (RFC 1876 section 3): latitude and longitude as degrees
+    // with optional minutes and seconds and a hemisphere, the altitude,
+    // and optional size and precisions, in meters with an optional "m"
+    fn loc(&self) -> Result<Loc, RDataError> {
+        self.expect_range(5, 12)?;
+
+        let mut i = 0;
+        let latitude = self.coordinate(&mut i, LOC_MAX_LATITUDE, "N", "S")?;
+        let longitude = self.coordinate(&mut i, LOC_MAX_LONGITUDE,
+                                        "E", "W")?;
+        let altitude = self.meters(i, "altitude", -LOC_ALTITUDE_BASE,
+                                   u32::MAX as i64 - LOC_ALTITUDE_BASE)?;
+
+        let mut precision = [LOC_DEFAULT_SIZE, LOC_DEFAULT_HORIZ_PRE,
+                             LOC_DEFAULT_VERT_PRE];
+        let what = ["size", "horizontal precision", "vertical precision"];
+        for n in 0..3 {
+            if i + 1 + n < self.data.len() {
+                let cm = self.meters(i + 1 + n, what[n], 0, 9_000_000_000)?;
+                precision[n] = encode_precision(cm);
+            }
+        }
+        if i + 4 < self.data.len() {
+            return Err(RDataError::FieldCount(self.rrtype, self.data.len()));
+        }
+
+        return Ok(Loc {
+            size: precision[0],
+            horiz_pre: precision[1],
+            vert_pre: precision[2],
+            latitude: (LOC_EQUATOR + latitude) as u32,
+            longitude: (LOC_EQUATOR + longitude) as u32,
+            altitude: (LOC_ALTITUDE_BASE + altitude) as u32,
+        });
+    }
+
+    // Degrees, optional minutes and seconds, and a hemisphere from field
+    // *i, moving i past them. In thousandths of an arc second, negative
+    // in the second hemisphere.
+    fn coordinate(&self, i: &mut usize, max: i64, pos: &str, neg: &str)
+                  -> Result<i64, RDataError> {
+        let start = *i;
+        let mut parts = vec!();
+
+        let sign = loop {
+            let s = self.text(*i)?;
+            *i += 1;
+
+            if s.eq_ignore_ascii_case(pos) {
+                break 1;
+            } else if s.eq_ignore_ascii_case(neg) {
+                break -1;
+            } else if parts.len() == 3 {
+                return Err(RDataError::BadField(
+                    *i - 1, format!("Expected {} or {}", pos, neg)));
+            }
+            parts.push(s);
+        };
+
+        let bad = |n: usize, what: &str| RDataError::BadField(
+            start + n, format!("Bad {} {}", what, parts[n]));
+        let degrees: i64 = match parts.first() {
+            Some(d) => number(d).filter(|d| (0..=180).contains(d))
+                .ok_or_else(|| bad(0, "degrees"))?,
+            None => {
+                return Err(RDataError::BadField(
+                    start, "Missing degrees".to_string()));
+            },
+        };
+        let minutes: i64 = match parts.get(1) {
+            Some(m) => number(m).filter(|m| (0..60).contains(m))
+                .ok_or_else(|| bad(1, "minutes"))?,
+            None => 0,
+        };
+        let millis = match parts.get(2) {
+            Some(s) => decimal(s, 3).filter(|s| (0..60_000).contains(s))
+                .ok_or_else(|| bad(2, "seconds"))?,
+            None => 0,
+        };
+
+        let value = (degrees * 60 + minutes) * 60_000 + millis;
+        if value > max {
+            return Err(RDataError::BadField(
+                start, format!("{} {} out of range", parts.join(" "), pos)));
+        }
+
+        return Ok(sign * value);
+    }
+
+    // Meters with an optional "m" and up to two decimals, in
+    // centimeters between min and max
+    fn meters(&self, i: usize, what: &str, min: i64, max: i64)
+              -> Result<i64, RDataError> {
+        let s = self.text(i)?;
+
+        return decimal(s.strip_suffix('m').unwrap_or(s), 2)
+            .filter(|cm| (min..=max).contains(cm))
+            .ok_or_else(|| RDataError::BadField(
+                i, format!("Bad {} {}", what, s)));
+    }
+
+    // Service parameters from field i, as key=value or key alone
+    fn svc_params(&self, i: usize) -> Result<Vec<SvcParam>, RDataError> {
+        let mut params: Vec<SvcParam> = vec!();
+
+        for j in i..self.data.len() {
+            let field = self.data[j].as_bytes();
+            let (key, value) = match field.iter().position(|&b| b == b'=') {
+                Some(n) => (&field[..n], Some(&field[n + 1..])),
+                None => (field, None),
+            };
+            let param = svc_param(key, value)
+                .map_err(|e| RDataError::BadField(j, e))?;
+
+            if params.iter().any(|p| p.key() == param.key()) {
+                return Err(RDataError::BadField(j, format!(
+                    "Duplicate key {}", svc_key_name(param.key()))));
+            }
+            params.push(param);
+        }
+
+        params.sort_by_key(|p| p.key());
+
+        return Ok(params);
+    }
+
+    // Data in the generic format: "#", the length and hex data, which
+    // may be split in several fields
+    fn generic(&self) -> Result<Vec<u8>, RDataError> {
+        self.expect_range(2, usize::MAX)?;
+        let len: usize = self.number(1, "length")?;
+
+        let data = match self.data.len() {
+            2 => vec!(),
+            _ => self.hex(2)?,
+        };
+        if data.len() != len {
+            return Err(RDataError::BadField(
+                1, format!("Length {} does not match the data", len)));
+        }
+
+        return Ok(data);
+    }
+}
+
+// Digest lengths by TLSA and SMIMEA matching type (SHA-256 and
+// SHA-512), and by SSHFP fingerprint type (SHA-1 and SHA-256)
+const TLSA_DIGESTS: [(u8, usize); 2] = [(1, 32), (2, 64)];
+const SSHFP_DIGESTS: [(u8, usize); 2] = [(1, 20), (2, 32)];
+
+// ZONEMD digest lengths by hash algorithm (SHA-384 and SHA-512). Other
+// digests must have at least 12 octets.
+const ZONEMD_DIGESTS: [(u8, usize); 2] = [(1, 48), (2, 64)];
+const ZONEMD_MIN_DIGEST: usize = 12;
+
+// TSIG and TKEY error mnemonics (RFC 8945 and RFC 2930), from the
+// RCODE registry
+const TSIG_ERRORS: [(u16, &str); 19] = [
+    (0, "NOERROR"),
+    (1, "FORMERR"),
+    (2, "SERVFAIL"),
+    (3, "NXDOMAIN"),
+    (4, "NOTIMP"),
+    (5, "REFUSED"),
+    (6, "YXDOMAIN"),
+    (7, "YXRRSET"),
+    (8, "NXRRSET"),
+    (9, "NOTAUTH"),
+    (10, "NOTZONE"),
+    (16, "BADSIG"),
+    (17, "BADKEY"),
+    (18, "BADTIME"),
+    (19, "BADMODE"),
+    (20, "BADNAME"),
+    (21, "BADALG"),
+    (22, "BADTRUNC"),
+    (23, "BADCOOKIE"),
+];
+
+// Largest TSIG time signed
+const MAX_TIME_SIGNED: u64 = (1 << 48) - 1;
+
+// Mnemonic from a table, or the number if it has none
+fn mnemonic_text<T: PartialEq + Display>(n: T, table: &[(T, &str)])
+                                         -> RecordData {
+    return match table.iter().find(|(t, _)| *t == n) {
+        Some((_, mnemonic)) => RecordData::new(mnemonic),
+        None => RecordData::new(&n.to_string()),
+    };
+}
+
+// CERT type mnemonics (RFC 4398 section 2.1)
+const CERT_TYPES: [(u16, &str); 10] = [
+    (1, "PKIX"),
+    (2, "SPKI"),
+    (3, "PGP"),
+    (4, "IPKIX"),
+    (5, "ISPKI"),
+    (6, "IPGP"),
+    (7, "ACPKIX"),
+    (8, "IACPKIX"),
+    (253, "URI"),
+    (254, "OID"),
+];
+
+// Label of an issuer domain name or parameter tag in a CAA issue value:
+// letters and digits, with inner hyphens
+fn is_caa_label(label: &str) -> bool {
+    return !label.is_empty() && !label.starts_with('-') &&
+        !label.ends_with('-') &&
+        label.bytes().all(|b| b.is_ascii_alphanumeric() || b == b'-');
+}
+
+// Check a CAA issue or issuewild value (RFC 8659 section 4.2): an
+// optional issuer domain name, and parameters after ";"
+fn check_caa_issue(value: &[u8]) -> Result<(), String> {
+    let value = std::str::from_utf8(value).ok().filter(|v| v.is_ascii())
+        .ok_or_else(|| "Non-ASCII issuer".to_string())?;
+    let mut parts = value.split(';').map(|p| p.trim_matches([' ', '\t']));
+
+    let issuer = parts.next().unwrap_or("");
+    if !issuer.is_empty() && !issuer.split('.').all(is_caa_label) {
+        return Err(format!("Bad issuer {}", issuer));
+    }
+
+    let parameters = parts.collect::<Vec<_>>();
+    if parameters == [""] {
+        return Ok(());
+    }
+    for parameter in parameters {
+        if parameter.is_empty() {
+            return Err("Empty parameter".to_string());
+        }
+        let (tag, value) = parameter.split_once('=').unwrap_or(("", ""));
+        let (tag, value) = (tag.trim_end_matches([' ', '\t']),
+                            value.trim_start_matches([' ', '\t']));
+
+        if !is_caa_label(tag) || value.bytes().any(|b| !b.is_ascii_graphic()
+                                                    || b == b';') {
+            return Err(format!("Bad parameter {}", parameter));
+        }
+    }
+
+    return Ok(());
+}
+
+// Check a CAA iodef value (RFC 8659 section 4.4): a mailto, http or
+// https URL
+fn check_caa_iodef(value: &[u8]) -> Result<(), String> {
+    let url = String::from_utf8_lossy(value);
+    let (scheme, rest) = url.split_once(':').unwrap_or(("", ""));
+
+    let valid = match scheme.to_ascii_lowercase().as_str() {
+        "mailto" => rest.contains('@'),
+        "http" | "https" => rest.len() > 2 && rest.starts_with("//"),
+        _ => false,
+    };
+    if !valid || url.bytes().any(|b| !b.is_ascii_graphic()) {
+        return Err(format!("Bad iodef URL {}", url));
+    }
+
+    return Ok(());
+}
+
+// Unsigned decimal number. FromStr for integers also accepts a sign.
+pub(crate) fn number<T: std::str::FromStr>(s: &str) -> Option<T> {
+    if s.is_empty() || !s.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+
+    return s.parse().ok();
+}
+
+// Decimal number with up to `places` decimals, in units of 10^-places
+fn decimal(s: &str, places: u32) -> Option<i64> {
+    let (sign, s) = match s.strip_prefix('-') {
+        Some(s) => (-1, s),
+        None => (1, s),
+    };
+    let (int, frac) = s.split_once('.').unwrap_or((s, ""));
+
+    if int.is_empty() || frac.len() > places as usize ||
+        !int.bytes().chain(frac.bytes()).all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+
+    let mut value: i64 = number(int)?;
+    for n in 0..places as usize {
+        let digit = frac.as_bytes().get(n).map_or(0, |b| b - b'0');
+        value = value.checked_mul(10)?.checked_add(digit as i64)?;
+    }
+
+    return Some(sign * value);
+}
+
+// LOC size or precision in centimeters as a mantissa and power of ten,
+// rounded down (RFC 1876 appendix A)
+fn encode_precision(cm: i64) -> u8 {
+    let mut exponent = 0;
+    while exponent < 9 && cm >= 10i64.pow(exponent + 1) {
+        exponent += 1;
+    }
+    let mantissa = (cm / 10i64.pow(exponent)).min(9);
+
+    return (mantissa << 4) as u8 | exponent as u8;
+}
+
+fn decode_precision(precision: u8) -> i64 {
+    return (precision >> 4) as i64 * 10i64.pow((precision & 0xf) as u32);
+}
+
+// Centimeters as meters with two decimals, or none if whole
+fn format_meters(cm: i64) -> String {
+    let sign = if cm < 0 { "-" } else { "" };
+
+    return match cm.abs() % 100 {
+        0 => format!("{}{}m", sign, cm.abs() / 100),
+        frac => format!("{}{}.{:02}m", sign, cm.abs() / 100, frac),
+    };
+}
+
+// LOC coordinate as degrees, minutes, seconds and hemisphere
+fn format_coordinate(value: u32, pos: char, neg: char) -> String {
+    let value = value as i64 - LOC_EQUATOR;
+    let hemisphere = if value < 0 { neg } else { pos };
+    let value = value.abs();
+
+    return format!("{} {} {}.{:03} {}", value / 3_600_000,
+                   value / 60_000 % 60, value % 60_000 / 1000,
+                   value % 1000, hemisphere);
+}
+
+// Service parameter keys of SVCB and HTTPS records (RFC 9460 section
+// 14.3.2, RFC 9461)
+const SVC_PARAM_KEYS: [(u16, &str); 8] = [
+    (0, "mandatory"),
+    (1, "alpn"),
+    (2, "no-default-alpn"),
+    (3, "port"),
+    (4, "ipv4hint"),
+    (5, "ech"),
+    (6, "ipv6hint"),
+    (7, "dohpath"),
+];
+
+// Service parameter key by name or keyNNNNN. Key 65535 is reserved.
+fn svc_key(name: &str) -> Option<u16> {
+    if let Some(&(key, _)) = SVC_PARAM_KEYS.iter()
+        .find(|(_, n)| *n == name) {
+        return Some(key);
+    }
+
+    return number(name.strip_prefix("key")?).filter(|&key| key != 65535);
+}
+
+fn svc_key_name(key: u16) -> String {
+    return match SVC_PARAM_KEYS.iter().find(|(k, _)| *k == key) {
+        Some((_, name)) => name.to_string(),
+        None => format!("key{}", key),
+    };
+}
+
+// Service parameter from the key and value of a field
+fn svc_param(key: &[u8], value: Option<&[u8]>) -> Result<SvcParam, String> {
+    let key = std::str::from_utf8(key).ok().and_then(svc_key).ok_or_else(
+        || format!("Bad key {}", String::from_utf8_lossy(key)))?;
+    let name = svc_key_name(key);
+    let bytes = || value.ok_or_else(
+        || format!("Missing value for {}", name));
+    let text = || std::str::from_utf8(bytes()?).map_err(
+        |_| format!("Invalid UTF-8 in {}", name));
+    let list = || value_list(bytes()?).ok_or_else(
+        || format!("Bad value list for {}", name));
+
+    let param = match key {
+        0 => {
+            let keys = list()?.iter().map(|k| {
+                std::str::from_utf8(k).ok().and_then(svc_key).ok_or_else(
+                    || format!("Bad key {}", String::from_utf8_lossy(k)))
+            }).collect::<Result<_, _>>()?;
+            SvcParam::Mandatory(keys)
+        },
+        1 => {
+            let ids = list()?;
+            if ids.iter().any(|id| id.len() > MAX_STRING_LEN) {
+                return Err("ALPN identifier too long".to_string());
+            }
+            SvcParam::Alpn(ids)
+        },
+        2 => {
+            if value.is_some_and(|v| !v.is_empty()) {
+                return Err("Value for no-default-alpn".to_string());
+            }
+            SvcParam::NoDefaultAlpn
+        },
+        3 => {
+            let port = text()?;
+            SvcParam::Port(number(port).ok_or_else(
+                || format!("Bad port {}", port))?)
+        },
+        4 => SvcParam::Ipv4Hint(addresses(&list()?)?),
+        5 => {
+            let ech = text()?;
+            SvcParam::Ech(decode_base64(ech).ok_or_else(
+                || format!("Bad base64 data {}", ech))?)
+        },
+        6 => SvcParam::Ipv6Hint(addresses(&list()?)?),
+        7 => SvcParam::DohPath(text()?.to_string()),
+        _ => SvcParam::Key(key, value.unwrap_or_default().to_vec()),
+    };
+
+    return Ok(param);
+}
+
+// IPv4 or IPv6 addresses from the items of a value list
+fn addresses<T: std::str::FromStr>(items: &[Vec<u8>])
+                                   -> Result<Vec<T>, String> {
+    return items.iter().map(|a| {
+        let a = String::from_utf8_lossy(a);
+        a.parse().map_err(|_| format!("Bad address {}", a))
+    }).collect();
+}
+
+// Split a value list at commas (RFC 9460 appendix A.1). The value is
+// already unescaped as a character-string, and "\," and "\\" are left
+// for a comma or backslash in an item. Items can't be empty.
+fn value_list(value: &[u8]) -> Option<Vec<Vec<u8>>> {
+    let mut items = vec!();
+    let mut item = vec!();
+    let mut i = 0;
+
+    while i < value.len() {
+        match value[i] {
+            b'\\' => {
+                item.push(*value.get(i + 1)?);
+                i += 2;
+            },
+            b',' => {
+                if item.is_empty() {
+                    return None;
+                }
+                items.push(std::mem::take(&mut item));
+                i += 1;
+            },
+            b => {
+                item.push(b);
+                i += 1;
+            },
+        }
+    }
+
+    if item.is_empty() {
+        return None;
+    }
+    items.push(item);
+
+    return Some(items);
+}
+
+// Check the mode and mandatory keys of service parameters. Parameters
+// are fields from 2, sorted by key.
+fn check_svc_params(priority: u16, params: &[SvcParam])
+                    -> Result<(), RDataError> {
+    let bad = |message: &str| RDataError::BadField(2, message.to_string());
+    let has_key = |key| params.iter().any(|p| p.key() == key);
+
+    if priority == 0 {
+        if !params.is_empty() {
+            return Err(bad("Parameters in AliasMode"));
+        }
+        return Ok(());
+    }
+
+    if let Some(SvcParam::Mandatory(keys)) = params.first() {
+        for (n, &key) in keys.iter().enumerate() {
+            if key == 0 {
+                return Err(bad("Mandatory lists mandatory"));
+            }
+            if keys[..n].contains(&key) {
+                return Err(bad(&format!("Mandatory lists {} twice",
+                                        svc_key_name(key))));
+            }
+            if !has_key(key) {
+                return Err(bad(&format!("Mandatory key {} missing",
+                                        svc_key_name(key))));
+            }
+        }
+    }
+
+    if has_key(2) && !has_key(1) {
+        return Err(bad("no-default-alpn without alpn"));
+    }
+
+    return Ok(());
+}
+
+// Wire format of a service parameter value (RFC 9460 appendix A.2)
+fn svc_param_wire(param: &SvcParam) -> Vec<u8> {
+    let mut w = Wire::default();
+
+    match param {
+        SvcParam::Mandatory(keys) => {
+            // In increasing order
+            let mut keys = keys.clone();
+            keys.sort();
+            keys.iter().for_each(|k| w.u16(*k));
+        },
+        SvcParam::Alpn(ids) => ids.iter().for_each(|id| w.string(id)),
+        SvcParam::NoDefaultAlpn => { },
+        SvcParam::Port(port) => w.u16(*port),
+        SvcParam::Ipv4Hint(addrs) => {
+            addrs.iter().for_each(|a| w.bytes(&a.octets()));
+        },
+        SvcParam::Ech(ech) => w.bytes(ech),
+        SvcParam::Ipv6Hint(addrs) => {
+            addrs.iter().for_each(|a| w.bytes(&a.octets()));
+        },
+        SvcParam::DohPath(path) => w.bytes(path.as_bytes()),
+        SvcParam::Key(_, value) => w.bytes(value),
+    }
+
+    return w.0;
+}
+
+// Presentation format of a service parameter. Value lists are escaped
+// for RecordData, which escapes the backslashes once more.
+fn svc_param_text(param: &SvcParam) -> Vec<u8> {
+    let list = |items: &mut dyn Iterator<Item = Vec<u8>>| {
+        let mut value = vec!();
+        for (n, item) in items.enumerate() {
+            if n > 0 {
+                value.push(b',');
+            }
+            for b in item {
+                if b == b',' || b == b'\\' {
+                    value.push(b'\\');
+                }
+                value.push(b);
+            }
+        }
+        value
+    };
+    let value = match param {
+        SvcParam::Mandatory(keys) => Some(list(&mut keys.iter().map(
+            |&k| svc_key_name(k).into_bytes()))),
+        SvcParam::Alpn(ids) => Some(list(&mut ids.iter().cloned())),
+        SvcParam::NoDefaultAlpn => None,
+        SvcParam::Port(port) => Some(port.to_string().into_bytes()),
+        SvcParam::Ipv4Hint(addrs) => Some(list(&mut addrs.iter().map(
+            |a| a.to_string().into_bytes()))),
+        SvcParam::Ech(ech) => Some(encode_base64(ech).into_bytes()),
+        SvcParam::Ipv6Hint(addrs) => Some(list(&mut addrs.iter().map(
+            |a| a.to_string().into_bytes()))),
+        SvcParam::DohPath(path) => Some(path.clone().into_bytes()),
+        SvcParam::Key(_, value) if value.is_empty() => None,
+        SvcParam::Key(_, value) => Some(value.clone()),
+    };
+
+    let mut text = svc_key_name(param.key()).into_bytes();
+    if let Some(value) = value {
+        text.push(b'=');
+        text.extend(value);
+    }
+
+    return text;
+}
+
+// DNSSEC algorithm mnemonics (RFC 4034 appendix A.1, and later RFCs)
+const ALGORITHMS: [(u8, &str); 15] = [
+    (1, "RSAMD5"),
+    (2, "DH"),
+    (3, "DSA"),
+    (5, "RSASHA1"),
+    (6, "DSA-NSEC3-SHA1"),
+    (7, "RSASHA1-NSEC3-SHA1"),
+    (8, "RSASHA256"),
+    (10, "RSASHA512"),
+    (12, "ECC-GOST"),
+    (13, "ECDSAP256SHA256"),
+    (14, "ECDSAP384SHA384"),
+    (15, "ED25519"),
+    (16, "ED448"),
+    (253, "PRIVATEDNS"),
+    (254, "PRIVATEOID"),
+];
+
+fn is_leap_year(year: i64) -> bool {
+    return (year % 4 == 0 && year % 100 != 0) || year % 400 == 0;
+}
+
+fn days_in_month(year: i64, month: i64) -> i64 {
+    return match month {
+        2 if is_leap_year(year) => 29,
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        _ => 31,
+    };
+}
+
+// Days since 1970-01-01 of a date in the proleptic Gregorian calendar
+fn days_from_civil(year: i64, month: i64, day: i64) -> i64 {
+    let y = if month <= 2 { year - 1 } else { year };
+    let era = y.div_euclid(400);
+    let yoe = y - era * 400;
+    let doy = (153 * ((month + 9) % 12) + 2) / 5 + day - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+
+    return era * 146097 + doe - 719468;
+}
+
+// Time as YYYYMMDDHHmmSS
+fn format_time(t: u32) -> String {
+    let t = t as i64;
+    let (days, secs) = (t / 86400, t % 86400);
+
+    // Inverse of days_from_civil
+    let z = days + 719468;
+    let era = z.div_euclid(146097);
+    let doe = z - era * 146097;
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + if month <= 2 { 1 } else { 0 };
+
+    return format!("{:04}{:02}{:02}{:02}{:02}{:02}", year, month, day,
+                   secs / 3600, secs % 3600 / 60, secs % 60);
+}
+
+// Type mnemonic, or TYPEnnn for unknown types
+fn type_name(rrtype: RRType) -> String {
+    return match rrtype {
+        RRType::Unknown(n) => format!("TYPE{}", n),
+        t => t.to_string(),
+    };
+}
+
+impl Record {
+    // The record data by type. Domain names are as in the record, i.e.
+    // relative if the parser was told to keep them as written.
+    pub fn rdata(&self) -> Result<RData, RDataError> {
+        let f = Fields {
+            rrtype: self.rrtype,
+            data: &self.data,
+        };
+
+        if self.generic {
+            return Ok(RData::Generic(f.generic()?));
+        }
+
+        let rdata = match self.rrtype {
+            RRType::A => {
+                f.expect(1)?;
+                RData::A(f.parse(0, "IPv4 address")?)
+            },
+            RRType::AAAA => {
+                f.expect(1)?;
+                RData::AAAA(f.parse(0, "IPv6 address")?)
+            },
+            RRType::NS => {
+                f.expect(1)?;
+                RData::NS(f.name(0)?)
+            },
+            RRType::CNAME => {
+                f.expect(1)?;
+                RData::CNAME(f.name(0)?)
+            },
+            RRType::DNAME => {
+                f.expect(1)?;
+                RData::DNAME(f.name(0)?)
+            },
+            RRType::PTR => {
+                f.expect(1)?;
+                RData::PTR(f.name(0)?)
+            },
+            RRType::MX => {
+                f.expect(2)?;
+                RData::MX(Mx {
+                    preference: f.u16(0)?,
+                    exchange: f.name(1)?,
+                })
+            },
+            RRType::SOA => {
+                f.expect(7)?;
+                RData::SOA(Soa {
+                    mname: f.name(0)?,
+                    rname: f.name(1)?,
+                    serial: f.u32(2)?,
+                    refresh: f.u32(3)?,
+                    retry: f.u32(4)?,
+                    expire: f.u32(5)?,
+                    minimum: f.u32(6)?,
+                })
+            },
+            RRType::TXT => RData::TXT(f.strings()?),
+            RRType::SPF => RData::SPF(f.strings()?),
+            RRType::WALLET => RData::WALLET(f.strings()?),
+            RRType::SRV => {
+                f.expect(4)?;
+                RData::SRV(Srv {
+                    priority: f.u16(0)?,
+                    weight: f.u16(1)?,
+                    port: f.u16(2)?,
+                    target: f.name(3)?,
+                })
+            },
+            RRType::HINFO => {
+                f.expect(2)?;
+                RData::HINFO(Hinfo {
+                    cpu: f.string(0)?,
+                    os: f.string(1)?,
+                })
+            },
+            RRType::DNSKEY | RRType::CDNSKEY | RRType::KEY => {
+                let key = Dnskey {
+                    flags: f.u16(0)?,
+                    protocol: f.u8(1)?,
+                    algorithm: f.algorithm(2)?,
+                    public_key: f.base64(3)?,
+                };
+                match self.rrtype {
+                    RRType::DNSKEY => RData::DNSKEY(key),
+                    RRType::CDNSKEY => RData::CDNSKEY(key),
+                    _ => RData::KEY(key),
+                }
+            },
+            RRType::DS | RRType::CDS | RRType::DLV | RRType::TA => {
+                let ds = Ds {
+                    key_tag: f.u16(0)?,
+                    algorithm: f.algorithm(1)?,
+                    digest_type: f.u8(2)?,
+                    digest: f.hex(3)?,
+                };
+                match self.rrtype {
+                    RRType::DS => RData::DS(ds),
+                    RRType::CDS => RData::CDS(ds),
+                    RRType::DLV => RData::DLV(ds),
+                    _ => RData::TA(ds),
+                }
+            },
+            RRType::RRSIG | RRType::SIG => {
+                let sig = Rrsig {
+                    type_covered: f.rrtype(0)?,
+                    algorithm: f.algorithm(1)?,
+                    labels: f.u8(2)?,
+                    original_ttl: f.u32(3)?,
+                    expiration: f.time(4)?,
+                    inception: f.time(5)?,
+                    key_tag: f.u16(6)?,
+                    signer: f.name(7)?,
+                    signature: f.base64(8)?,
+                };
+                match self.rrtype {
+                    RRType::RRSIG => RData::RRSIG(sig),
+                    _ => RData::SIG(sig),
+                }
+            },
+            RRType::NSEC => {
+                RData::NSEC(Nsec {
+                    next: f.name(0)?,
+                    types: f.types(1)?,
+                })
+            },
+            RRType::NSEC3 => {
+                f.expect_range(5, usize::MAX)?;
+                let next_hashed = f.text(4)?;
+                RData::NSEC3(Nsec3 {
+                    hash_algorithm: f.u8(0)?,
+                    flags: f.u8(1)?,
+                    iterations: f.u16(2)?,
+                    salt: f.salt(3)?,
+                    next_hashed: decode_base32hex(next_hashed).ok_or_else(
+                        || RDataError::BadField(
+                            4, format!("Bad base32 data {}", next_hashed)))?,
+                    types: f.types(5)?,
+                })
+            },
+            RRType::NSEC3PARAM => {
+                f.expect(4)?;
+                RData::NSEC3PARAM(Nsec3Param {
+                    hash_algorithm: f.u8(0)?,
+                    flags: f.u8(1)?,
+                    iterations: f.u16(2)?,
+                    salt: f.salt(3)?,
+                })
+            },
+            RRType::LOC => RData::LOC(f.loc()?),
+            RRType::CAA => {
+                f.expect(3)?;
+                let flags = f.u8(0)?;
+                let tag = f.text(1)?;
+                if tag.is_empty() || tag.len() > 15 ||
+                    !tag.bytes().all(|b| b.is_ascii_alphanumeric()) {
+                    return Err(RDataError::BadField(
+                        1, format!("Bad tag {}", tag)));
+                }
+                let value = f.data[2].as_bytes().to_vec();
+                match tag.to_ascii_lowercase().as_str() {
+                    "issue" | "issuewild" => check_caa_issue(&value),
+                    "iodef" => check_caa_iodef(&value),
+                    _ => Ok(()),
+                }.map_err(|e| RDataError::BadField(2, e))?;
+                RData::CAA(Caa {
+                    flags: flags,
+                    tag: tag.to_string(),
+                    value: value,
+                })
+            },
+            RRType::TLSA | RRType::SMIMEA => {
+                let matching_type = f.u8(2)?;
+                let tlsa = Tlsa {
+                    usage: f.u8(0)?,
+                    selector: f.u8(1)?,
+                    matching_type: matching_type,
+                    data: f.digest(3, matching_type, &TLSA_DIGESTS)?,
+                };
+                match self.rrtype {
+                    RRType::TLSA => RData::TLSA(tlsa),
+                    _ => RData::SMIMEA(tlsa),
+                }
+            },
+            RRType::SSHFP => {
+                let fingerprint_type = f.u8(1)?;
+                RData::SSHFP(Sshfp {
+                    algorithm: f.u8(0)?,
+                    fingerprint_type: fingerprint_type,
+                    fingerprint: f.digest(2, fingerprint_type,
+                                          &SSHFP_DIGESTS)?,
+                })
+            },
+            RRType::OPENPGPKEY => RData::OPENPGPKEY(f.base64(0)?),
+            RRType::CERT => {
+                RData::CERT(Cert {
+                    cert_type: f.mnemonic(0, &CERT_TYPES,
+                                          "certificate type")?,
+                    key_tag: f.u16(1)?,
+                    algorithm: f.algorithm(2)?,
+                    certificate: f.base64(3)?,
+                })
+            },
+            RRType::SVCB | RRType::HTTPS => {
+                f.expect_range(2, usize::MAX)?;
+                let svcb = Svcb {
+                    priority: f.u16(0)?,
+                    target: f.name(1)?,
+                    params: f.svc_params(2)?,
+                };
+                check_svc_params(svcb.priority, &svcb.params)?;
+                match self.rrtype {
+                    RRType::SVCB => RData::SVCB(svcb),
+                    _ => RData::HTTPS(svcb),
+                }
+            },
+            RRType::RP => {
+                f.expect(2)?;
+                RData::RP(Rp {
+                    mbox: f.name(0)?,
+                    txt: f.name(1)?,
+                })
+            },
+            RRType::AFSDB => {
+                f.expect(2)?;
+                RData::AFSDB(Afsdb {
+                    subtype: f.u16(0)?,
+                    hostname: f.name(1)?,
+                })
+            },
+            RRType::NAPTR => {
+                f.expect(6)?;
+                let flags = f.string(2)?;
+                if !flags.iter().all(|b| b.is_ascii_alphanumeric()) {
+                    return Err(RDataError::BadField(2, format!(
+                        "Bad flags {}", String::from_utf8_lossy(&flags))));
+                }
+                RData::NAPTR(Naptr {
+                    order: f.u16(0)?,
+                    preference: f.u16(1)?,
+                    flags: flags,
+                    services: f.string(3)?,
+                    regexp: f.string(4)?,
+                    replacement: f.name(5)?,
+                })
+            },
+            RRType::KX => {
+                f.expect(2)?;
+                RData::KX(Mx {
+                    preference: f.u16(0)?,
+                    exchange: f.name(1)?,
+                })
+            },
+            RRType::APL => {
+                RData::APL((0..self.data.len()).map(|i| f.apl_item(i))
+                           .collect::<Result<_, _>>()?)
+            },
+            RRType::IPSECKEY => {
+                f.expect_range(4, usize::MAX)?;
+                let gateway_type = f.u8(1)?;
+                let gateway = match gateway_type {
+                    0 if f.text(3)? == "." => IpseckeyGateway::None,
+                    0 => {
+                        return Err(RDataError::BadField(
+                            3, "Gateway must be . for type 0".to_string()));
+                    },
+                    1 => IpseckeyGateway::Ipv4(f.parse(3, "IPv4 address")?),
+                    2 => IpseckeyGateway::Ipv6(f.parse(3, "IPv6 address")?),
+                    3 => IpseckeyGateway::Name(f.name(3)?),
+                    _ => {
+                        return Err(RDataError::BadField(1, format!(
+                            "Bad gateway type {}", gateway_type)));
+                    },
+                };
+                RData::IPSECKEY(Ipseckey {
+                    precedence: f.u8(0)?,
+                    algorithm: f.u8(2)?,
+                    gateway: gateway,
+                    public_key: match self.data.len() {
+                        4 => vec!(),
+                        _ => f.base64(4)?,
+                    },
+                })
+            },
+            RRType::DHCID => {
+                // Identifier type, digest type and digest
+                let dhcid = f.base64(0)?;
+                if dhcid.len() < 4 {
+                    return Err(RDataError::BadField(
+                        0, "DHCID data too short".to_string()));
+                }
+                RData::DHCID(dhcid)
+            },
+            RRType::HIP => {
+                f.expect_range(3, usize::MAX)?;
+                let (hit, public_key) = (f.text(1)?, f.text(2)?);
+                RData::HIP(Hip {
+                    algorithm: f.u8(0)?,
+                    hit: decode_hex(hit).ok_or_else(|| RDataError::BadField(
+                        1, format!("Bad hex data {}", hit)))?,
+                    public_key: decode_base64(public_key).ok_or_else(
+                        || RDataError::BadField(
+                            2, format!("Bad base64 data {}", public_key)))?,
+                    rendezvous_servers: (3..self.data.len())
+                        .map(|i| f.name(i)).collect::<Result<_, _>>()?,
+                })
+            },
+            RRType::CSYNC => {
+                RData::CSYNC(Csync {
+                    serial: f.u32(0)?,
+                    flags: f.u16(1)?,
+                    types: f.types(2)?,
+                })
+            },
+            RRType::ZONEMD => {
+                let hash_algorithm = f.u8(2)?;
+                let digest = f.digest(3, hash_algorithm, &ZONEMD_DIGESTS)?;
+                if digest.len() < ZONEMD_MIN_DIGEST {
+                    return Err(RDataError::BadField(3, format!(
+                        "Digest length {} is too short", digest.len())));
+                }
+                RData::ZONEMD(Zonemd {
+                    serial: f.u32(0)?,
+                    scheme: f.u8(1)?,
+                    hash_algorithm: hash_algorithm,
+                    digest: digest,
+                })
+            },
+            RRType::EUI48 => {
+                f.expect(1)?;
+                RData::EUI48(f.eui(0)?)
+            },
+            RRType::EUI64 => {
+                f.expect(1)?;
+                RData::EUI64(f.eui(0)?)
+            },
+            RRType::TKEY => {
+                let mut i = 5;
+                let tkey = Tkey {
+                    algorithm: f.name(0)?,
+                    inception: f.time(1)?,
+                    expiration: f.time(2)?,
+                    mode: f.u16(3)?,
+                    error: f.mnemonic(4, &TSIG_ERRORS, "error")?,
+                    key: f.sized_base64(&mut i)?,
+                    other: f.sized_base64(&mut i)?,
+                };
+                f.expect(i)?;
+                RData::TKEY(tkey)
+            },
+            RRType::TSIG => {
+                let time_signed: u64 = f.number(1, "48-bit value")?;
+                if time_signed > MAX_TIME_SIGNED {
+                    return Err(RDataError::BadField(1, format!(
+                        "Bad 48-bit value {}", time_signed)));
+                }
+                let mut i = 3;
+                let mac = f.sized_base64(&mut i)?;
+                let original_id = f.u16(i)?;
+                let error = f.mnemonic(i + 1, &TSIG_ERRORS, "error")?;
+                i += 2;
+                let other = f.sized_base64(&mut i)?;
+                f.expect(i)?;
+                RData::TSIG(Tsig {
+                    algorithm: f.name(0)?,
+                    time_signed: time_signed,
+                    fudge: f.u16(2)?,
+                    mac: mac,
+                    original_id: original_id,
+                    error: error,
+                    other: other,
+                })
+            },
+            RRType::URI => {
+                f.expect(3)?;
+                let target = f.data[2].as_bytes().to_vec();
+                if target.is_empty() {
+                    return Err(RDataError::BadField(
+                        2, "Empty target".to_string()));
+                }
+                RData::URI(Uri {
+                    priority: f.u16(0)?,
+                    weight: f.u16(1)?,
+                    target: target,
+                })
+            },
+            _ => {
+                return Err(RDataError::UnsupportedType(self.rrtype));
+            },
+        };
+
+        return Ok(rdata);
+    }
+
+    // Replace the record data. The type is set from the data, except
+    // for generic data.
+    pub fn set_rdata(&mut self, rdata: &RData) {
+        let name = |n: &DomainName| RecordData::new(&n.to_string());
+        let num = |n: &dyn Display| RecordData::new(&n.to_string());
+        let strings = |s: &Vec<Vec<u8>>| s.iter()
+            .map(|s| RecordData::from_bytes(s)).collect();
+        let types = |t: &TypeBitmap| t.types().into_iter()
+            .map(|t| RecordData::new(&type_name(t))).collect::<Vec<_>>();
+        let sized_base64 = |d: &[u8]| match d.is_empty() {
+            true => vec!(num(&0)),
+            false => vec!(num(&d.len()), RecordData::new(&encode_base64(d))),
+        };
+        let format_eui = |eui: &[u8]| RecordData::new(
+            &eui.iter().map(|b| format!("{:02x}", b))
+                .collect::<Vec<_>>().join("-"));
+        let salt = |s: &[u8]| match s.is_empty() {
+            true => RecordData::new("-"),
+            false => RecordData::new(&encode_hex(s)),
+        };
+
+        let (rrtype, data) = match rdata {
+            RData::A(a) => (RRType::A, vec!(num(a))),
+            RData::AAAA(a) => (RRType::AAAA, vec!(num(a))),
+            RData::NS(n) => (RRType::NS, vec!(name(n))),
+            RData::CNAME(n) => (RRType::CNAME, vec!(name(n))),
+            RData::DNAME(n) => (RRType::DNAME, vec!(name(n))),
+            RData::PTR(n) => (RRType::PTR, vec!(name(n))),
+            RData::MX(mx) => {
+                (RRType::MX, vec!(num(&mx.preference), name(&mx.exchange)))
+            },
+            RData::SOA(soa) => {
+                (RRType::SOA, vec!(name(&soa.mname), name(&soa.rname),
+                                   num(&soa.serial), num(&soa.refresh),
+                                   num(&soa.retry), num(&soa.expire),
+                                   num(&soa.minimum)))
+            },
+            RData::TXT(s) => (RRType::TXT, strings(s)),
+            RData::SPF(s) => (RRType::SPF, strings(s)),
+            RData::WALLET(s) => (RRType::WALLET, strings(s)),
+            RData::SRV(srv) => {
+                (RRType::SRV, vec!(num(&srv.priority), num(&srv.weight),
+                                   num(&srv.port), name(&srv.target)))
+            },
+            RData::HINFO(h) => {
+                (RRType::HINFO, vec!(RecordData::from_bytes(&h.cpu),
+                                     RecordData::from_bytes(&h.os)))
+            },
+            RData::DNSKEY(k) | RData::CDNSKEY(k) | RData::KEY(k) => {
+                let rrtype = match rdata {
+                    RData::DNSKEY(_) => RRType::DNSKEY,
+                    RData::CDNSKEY(_) => RRType::CDNSKEY,
+                    _ => RRType::KEY,
+                };
+                (rrtype, vec!(num(&k.flags), num(&k.protocol),
+                              num(&k.algorithm),
+                              RecordData::new(&encode_base64(&k.public_key))))
+            },
+            RData::DS(ds) | RData::CDS(ds) | RData::DLV(ds) |
+            RData::TA(ds) => {
+                let rrtype = match rdata {
+                    RData::DS(_) => RRType::DS,
+                    RData::CDS(_) => RRType::CDS,
+                    RData::DLV(_) => RRType::DLV,
+                    _ => RRType::TA,
+                };
+                (rrtype, vec!(num(&ds.key_tag), num(&ds.algorithm),
+                              num(&ds.digest_type),
+                              RecordData::new(&encode_hex(&ds.digest))))
+            },
+            RData::RRSIG(sig) | RData::SIG(sig) => {
+                let rrtype = match rdata {
+                    RData::RRSIG(_) => RRType::RRSIG,
+                    _ => RRType::SIG,
+                };
+                (rrtype, vec!(
+                    RecordData::new(&type_name(sig.type_covered)),
+                    num(&sig.algorithm), num(&sig.labels),
+                    num(&sig.original_ttl),
+                    RecordData::new(&format_time(sig.expiration)),
+                    RecordData::new(&format_time(sig.inception)),
+                    num(&sig.key_tag), name(&sig.signer),
+                    RecordData::new(&encode_base64(&sig.signature))))
+            },
+            RData::NSEC(nsec) => {
+                let mut data = vec!(name(&nsec.next));
+                data.extend(types(&nsec.types));
+                (RRType::NSEC, data)
+            },
+            RData::NSEC3(nsec3) => {
+                let mut data = vec!(
+                    num(&nsec3.hash_algorithm), num(&nsec3.flags),
+                    num(&nsec3.iterations), salt(&nsec3.salt),
+                    RecordData::new(&encode_base32hex(&nsec3.next_hashed)));
+                data.extend(types(&nsec3.types));
+                (RRType::NSEC3, data)
+            },
+            RData::NSEC3PARAM(p) => {
+                (RRType::NSEC3PARAM, vec!(
+                    num(&p.hash_algorithm), num(&p.flags),
+                    num(&p.iterations), salt(&p.salt)))
+            },
+            RData::LOC(loc) => {
+                let text = [
+                    format_coordinate(loc.latitude, 'N', 'S'),
+                    format_coordinate(loc.longitude, 'E', 'W'),
+                    format_meters(loc.altitude as i64 - LOC_ALTITUDE_BASE),
+                    format_meters(decode_precision(loc.size)),
+                    format_meters(decode_precision(loc.horiz_pre)),
+                    format_meters(decode_precision(loc.vert_pre)),
+                ].join(" ");
+                (RRType::LOC, text.split(' ').map(RecordData::new).collect())
+            },
+            RData::CAA(caa) => {
+                (RRType::CAA, vec!(num(&caa.flags), RecordData::new(&caa.tag),
+                                   RecordData::from_bytes(&caa.value)))
+            },
+            RData::TLSA(t) | RData::SMIMEA(t) => {
+                let rrtype = match rdata {
+                    RData::TLSA(_) => RRType::TLSA,
+                    _ => RRType::SMIMEA,
+                };
+                (rrtype, vec!(num(&t.usage), num(&t.selector),
+                              num(&t.matching_type),
+                              RecordData::new(&encode_hex(&t.data))))
+            },
+            RData::SSHFP(fp) => {
+                (RRType::SSHFP, vec!(
+                    num(&fp.algorithm), num(&fp.fingerprint_type),
+                    RecordData::new(&encode_hex(&fp.fingerprint))))
+            },
+            RData::OPENPGPKEY(key) => {
+                (RRType::OPENPGPKEY,
+                 vec!(RecordData::new(&encode_base64(key))))
+            },
+            RData::CERT(cert) => {
+                (RRType::CERT, vec!(
+                    mnemonic_text(cert.cert_type, &CERT_TYPES),
+                    num(&cert.key_tag), num(&cert.algorithm),
+                    RecordData::new(&encode_base64(&cert.certificate))))
+            },
+            RData::SVCB(svcb) | RData::HTTPS(svcb) => {
+                let rrtype = match rdata {
+                    RData::SVCB(_) => RRType::SVCB,
+                    _ => RRType::HTTPS,
+                };
+                let mut data = vec!(num(&svcb.priority), name(&svcb.target));
+                data.extend(svcb.params.iter().map(
+                    |p| RecordData::from_bytes(&svc_param_text(p))));
+                (rrtype, data)
+            },
+            RData::RP(rp) => {
+                (RRType::RP, vec!(name(&rp.mbox), name(&rp.txt)))
+            },
+            RData::AFSDB(a) => {
+                (RRType::AFSDB, vec!(num(&a.subtype), name(&a.hostname)))
+            },
+            RData::NAPTR(n) => {
+                (RRType::NAPTR, vec!(
+                    num(&n.order), num(&n.preference),
+                    RecordData::from_bytes(&n.flags),
+                    RecordData::from_bytes(&n.services),
+                    RecordData::from_bytes(&n.regexp), name(&n.replacement)))
+            },
+            RData::KX(kx) => {
+                (RRType::KX, vec!(num(&kx.preference), name(&kx.exchange)))
+            },
+            RData::APL(items) => {
+                (RRType::APL, items.iter().map(|item| {
+                    let family = match item.address {
+                        IpAddr::V4(_) => 1,
+                        IpAddr::V6(_) => 2,
+                    };
+                    RecordData::new(&format!(
+                        "{}{}:{}/{}", if item.negation { "!" } else { "" },
+                        family, item.address, item.prefix))
+                }).collect())
+            },
+            RData::IPSECKEY(key) => {
+                let (gateway_type, gateway) = match &key.gateway {
+                    IpseckeyGateway::None => (0, RecordData::new(".")),
+                    IpseckeyGateway::Ipv4(a) => (1, num(a)),
+                    IpseckeyGateway::Ipv6(a) => (2, num(a)),
+                    IpseckeyGateway::Name(n) => (3, name(n)),
+                };
+                let mut data = vec!(num(&key.precedence), num(&gateway_type),
+                                    num(&key.algorithm), gateway);
+                if !key.public_key.is_empty() {
+                    data.push(RecordData::new(&encode_base64(
+                        &key.public_key)));
+                }
+                (RRType::IPSECKEY, data)
+            },
+            RData::DHCID(d) => {
+                (RRType::DHCID, vec!(RecordData::new(&encode_base64(d))))
+            },
+            RData::HIP(hip) => {
+                let mut data = vec!(
+                    num(&hip.algorithm),
+                    RecordData::new(&encode_hex(&hip.hit)),
+                    RecordData::new(&encode_base64(&hip.public_key)));
+                data.extend(hip.rendezvous_servers.iter().map(name));
+                (RRType::HIP, data)
+            },
+            RData::CSYNC(c) => {
+                let mut data = vec!(num(&c.serial), num(&c.flags));
+                data.extend(types(&c.types));
+                (RRType::CSYNC, data)
+            },
+            RData::ZONEMD(z) => {
+                (RRType::ZONEMD, vec!(
+                    num(&z.serial), num(&z.scheme), num(&z.hash_algorithm),
+                    RecordData::new(&encode_hex(&z.digest))))
+            },
+            RData::EUI48(eui) => (RRType::EUI48, vec!(format_eui(eui))),
+            RData::EUI64(eui) => (RRType::EUI64, vec!(format_eui(eui))),
+            RData::TKEY(t) => {
+                let mut data = vec!(
+                    name(&t.algorithm),
+                    RecordData::new(&format_time(t.inception)),
+                    RecordData::new(&format_time(t.expiration)),
+                    num(&t.mode), mnemonic_text(t.error, &TSIG_ERRORS));
+                data.extend(sized_base64(&t.key));
+                data.extend(sized_base64(&t.other));
+                (RRType::TKEY, data)
+            },
+            RData::TSIG(t) => {
+                let mut data = vec!(name(&t.algorithm), num(&t.time_signed),
+                                    num(&t.fudge));
+                data.extend(sized_base64(&t.mac));
+                data.push(num(&t.original_id));
+                data.push(mnemonic_text(t.error, &TSIG_ERRORS));
+                data.extend(sized_base64(&t.other));
+                (RRType::TSIG, data)
+            },
+            RData::URI(uri) => {
+                (RRType::URI, vec!(num(&uri.priority), num(&uri.weight),
+                                   RecordData::from_bytes(&uri.target)))
+            },
+            RData::Generic(d) => {
+                (self.rrtype, vec!(RecordData::new("#"), num(&d.len()),
+                                   RecordData::new(&encode_hex(d))))
+            },
+        };
+
+        self.rrtype = rrtype;
+        self.data = data;
+        self.generic = matches!(rdata, RData::Generic(_));
+    }
+}
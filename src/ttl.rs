@@ -0,0 +1,104 @@
+use std::fmt::{Display, Formatter};
+use std::str::FromStr;
+
+// TTL value. Parses and displays the BIND unit syntax, where a number
+// is followed by one of the case insensitive units w, d, h, m or s,
+// e.g. "1w2d", "1h30m". A plain number is a value in seconds.
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq, Hash, Ord, PartialOrd)]
+pub struct Ttl(pub u32);
+
+// How the TTL of records without a TTL is found when there is no $TTL
+// directive. The $TTL directive (RFC 2308) always takes precedence.
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq)]
+pub enum TtlPolicy {
+    // Use the TTL of the last record with an explicit TTL (RFC 1035)
+    #[default]
+    Rfc1035,
+    // Use the minimum field of the SOA record, like older BIND versions
+    LegacySoa,
+    // A missing TTL is an error, so only $TTL is used (RFC 2308)
+    Strict,
+}
+
+const UNITS: [(char, u32); 5] = [
+    ('w', 7*24*3600),
+    ('d', 24*3600),
+    ('h', 3600),
+    ('m', 60),
+    ('s', 1),
+];
+
+impl FromStr for Ttl {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let bad_ttl = || format!("Bad TTL {}", s);
+
+        if s.is_empty() {
+            return Err(bad_ttl());
+        }
+
+        if s.bytes().all(|b| b.is_ascii_digit()) {
+            return Ok(Ttl(s.parse().or(Err(bad_ttl()))?));
+        }
+
+        let mut total: u64 = 0;
+        let mut number = String::new();
+
+        for c in s.chars() {
+            if c.is_ascii_digit() {
+                number.push(c);
+                continue;
+            }
+
+            // Each unit must be preceded by a number
+            let lc = c.to_ascii_lowercase();
+            let (_, seconds) = UNITS.iter().find(|(u, _)| *u == lc)
+                .ok_or_else(bad_ttl)?;
+            let n: u64 = number.parse().or(Err(bad_ttl()))?;
+            total = n.checked_mul(*seconds as u64)
+                .and_then(|t| t.checked_add(total))
+                .filter(|t| *t <= u32::MAX as u64)
+                .ok_or_else(bad_ttl)?;
+            number.clear();
+        }
+
+        // Trailing number without unit
+        if !number.is_empty() {
+            return Err(bad_ttl());
+        }
+
+        return Ok(Ttl(total as u32));
+    }
+}
+
+impl Display for Ttl {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        if self.0 == 0 {
+            return write!(f, "0s");
+        }
+
+        let mut remaining = self.0;
+
+        for (unit, seconds) in UNITS {
+            if remaining >= seconds {
+                write!(f, "{}{}", remaining / seconds, unit)?;
+                remaining %= seconds;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl From<u32> for Ttl {
+    fn from(ttl: u32) -> Self {
+        Ttl(ttl)
+    }
+}
+
+impl From<Ttl> for u32 {
+    fn from(ttl: Ttl) -> Self {
+        ttl.0
+    }
+}
@@ -1,9 +1,16 @@
 use std::fs::File;
 use std::io::{BufReader, BufRead};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, LazyLock};
 use std::fmt::{Display, Debug, Formatter};
 use std::collections::HashMap;
 use bstr::ByteSlice;
 use unit_enum::UnitEnum;
+use crate::generate::Generate;
+use crate::ttl::{Ttl, TtlPolicy};
+use crate::error::{ErrorKind, ParseError};
+use crate::name::{DomainName, CasePolicy};
+use crate::rdata::number;
 
 // Numeric representation for rrclass
 #[derive(Debug, Default, Copy, Clone, PartialEq, Eq, Ord, PartialOrd, UnitEnum)]
@@ -66,8 +73,9 @@ pub enum RRType {
     ZONEMD     = 63,
     SVCB       = 64,
     HTTPS      = 65,
+    SPF        = 99,
     EUI48      = 108,
-    EUI164     = 109,
+    EUI64      = 109,
     TKEY       = 249,
     TSIG       = 250,
     URI        = 256,
@@ -85,44 +93,219 @@ impl Display for RRType {
     }
 }
 
-#[derive(Debug, Clone)]
+impl RRType {
+    // The EUI64 type was named EUI164 in earlier versions
+    #[deprecated(note = "renamed to EUI64")]
+    pub const EUI164: RRType = RRType::EUI64;
+}
+
+// Data field of a record. The field is kept as the unescaped bytes of
+// the token or character-string, except domain names, which are kept
+// in presentation format with their escapes: unescaped, a\.b and a.b
+// would be the same bytes. Record::name_field tells which fields are
+// names.
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub struct RecordData {
-    pub data: String,
+    data: Vec<u8>,
 }
 
 impl RecordData {
     pub fn new(data: &str) -> Self {
 	Self {
-	    data: data.to_string(),
+	    data: data.as_bytes().to_vec(),
 	}
     }
 
     pub fn from_bytes(data: &[u8]) -> Self {
 	Self {
-	    data: data.escape_bytes().to_string(),
+	    data: data.to_vec(),
 	}
     }
-}
 
-impl PartialEq for RecordData {
-    fn eq(&self, other: &RecordData) -> bool {
-	return self.data == other.data;
+    pub fn as_bytes(&self) -> &[u8] {
+	return &self.data;
+    }
+
+    // The data as a string, if it is valid UTF-8
+    pub fn as_str(&self) -> Option<&str> {
+	return std::str::from_utf8(&self.data).ok();
     }
 }
 
 impl Display for RecordData {
+    // Presentation format. The data is quoted if it is empty or contains
+    // delimiters. Non-printable bytes are shown as \DDD.
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
-	write!(f, "{}", self.data)
+	let quote = self.data.is_empty() ||
+	    self.data.iter().any(|b| b" ()\";".contains(b));
+
+	if quote {
+	    write!(f, "\"")?;
+	}
+
+	for &b in &self.data {
+	    match b {
+		b'"' | b'\\' => write!(f, "\\{}", b as char)?,
+		0x21..=0x7e => write!(f, "{}", b as char)?,
+		b' ' if quote => write!(f, " ")?,
+		_ => write!(f, "\\{:03}", b)?,
+	    }
+	}
+
+	if quote {
+	    write!(f, "\"")?;
+	}
+
+	Ok(())
+    }
+}
+
+// Location of a record in the zonefile. Records in parentheses may
+// span several lines.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Span {
+    // File name, if the input is a named file
+    pub file: Option<Arc<Path>>,
+    pub start_line: usize,
+    pub end_line: usize,
+    // Byte offset of the first line, and the end of the last line
+    pub start_offset: usize,
+    pub end_offset: usize,
+}
+
+// True if the data field at the index is a domain name. Domain names
+// are kept in presentation format. Data in the generic format of RFC
+// 3597, starting with \#, has no names.
+fn rdata_name_field(rrtype: RRType, index: usize, data: &[RecordData],
+		    generic: bool) -> bool {
+    if generic {
+	return false;
     }
+
+    return match rrtype {
+	RRType::NS | RRType::CNAME | RRType::PTR | RRType::DNAME |
+	RRType::NSEC | RRType::TKEY | RRType::TSIG => index == 0,
+	RRType::MX | RRType::KX | RRType::AFSDB | RRType::SVCB |
+	RRType::HTTPS => index == 1,
+	RRType::SOA | RRType::RP => index < 2,
+	RRType::SRV => index == 3,
+	RRType::NAPTR => index == 5,
+	RRType::SIG | RRType::RRSIG => index == 7,
+	// Rendezvous servers
+	RRType::HIP => index >= 3,
+	// Gateway of type 3
+	RRType::IPSECKEY => {
+	    index == 3 && data.get(1).is_some_and(|d| d.as_bytes() == b"3")
+	},
+	_ => false,
+    };
+}
+
+// Window block and bitmap of each type, for the type bitmaps of NSEC
+// and NSEC3 records
+static RRTYPE_BITMAPS: LazyLock<HashMap<String, (u8, u128, u128)>> =
+    LazyLock::new(|| {
+        let mut bm_hash = HashMap::new();
+
+        for t in RRType::values() {
+            if t == RRType::None {
+                continue;
+            }
+
+            let t_str = format!("{:?}", t).to_lowercase();
+            bm_hash.insert(t_str, type_bitmap(t.discriminant()));
+        }
+
+        return bm_hash;
+    });
+
+// Window block and bitmap of a type number. The bitmap of the window is
+// split in two halves, with the lowest type number in the highest bit.
+fn type_bitmap(t_disc: u16) -> (u8, u128, u128) {
+    let window_block = (t_disc >> 8) as u8;
+    let bitpos = t_disc & 0xff;
+
+    if bitpos < 128 {
+        return (window_block, 1 << (127 - bitpos), 0);
+    }
+    else {
+        return (window_block, 0, 1 << (255 - bitpos));
+    }
+}
+
+// Bitmap of a type mnemonic or TYPEnnn
+pub(crate) fn rrtype_bitmap(rrtype_str: &str) -> Option<(u8, u128, u128)> {
+    let lcstr = rrtype_str.to_lowercase();
+
+    if let Some(bm) = RRTYPE_BITMAPS.get(&lcstr) {
+        return Some(*bm);
+    }
+
+    return lcstr.strip_prefix("type")
+        .and_then(number::<u16>)
+        .map(type_bitmap);
+}
+
+// Characters which end an unquoted token
+fn is_delimiter(b: u8) -> bool {
+    return b" \t\r\n();".contains(&b);
+}
+
+// End of an unquoted token starting at start. Escaped characters do not
+// end the token, but an escape can't continue on the next line. With
+// key_value, a quote after "=" starts a quoted value, as in the SVCB
+// parameter key="value".
+fn token_end(bytes: &[u8], start: usize, key_value: bool) -> usize {
+    let mut i = start;
+
+    while i < bytes.len() && !is_delimiter(bytes[i]) {
+        match (bytes[i], bytes.get(i + 1)) {
+            (b'\\', Some(b'\r' | b'\n')) | (b'\\', None) => i += 1,
+            (b'\\', Some(_)) => i += 2,
+            (b'"', _) if key_value && i > start && bytes[i - 1] == b'=' => {
+                i += 1;
+                while i < bytes.len() && bytes[i] != b'"' {
+                    i += if bytes[i] == b'\\' { 2 } else { 1 };
+                }
+                i += 1;
+            },
+            _ => i += 1,
+        }
+    }
+
+    return i.min(bytes.len());
+}
+
+// The value and length of the escape sequence at i, either an escaped
+// character (\X) or an escaped decimal value (\DDD)
+fn unescape_at(bytes: &[u8], i: usize) -> Option<(u8, usize)> {
+    return match bytes.get(i + 1) {
+        Some(d) if d.is_ascii_digit() => {
+            let value = bytes.get(i + 1..i + 4)
+                .filter(|ddd| ddd.iter().all(|b| b.is_ascii_digit()))
+                .and_then(|ddd| ddd.to_str().ok())
+                .and_then(|ddd| ddd.parse::<u8>().ok())?;
+            Some((value, 4))
+        },
+        Some(b'\r' | b'\n') | None => None,
+        Some(c) => Some((*c, 2)),
+    };
 }
 
 #[derive(Debug, Clone)]
 pub struct Record {
-    pub name: String,
+    pub name: DomainName,
     pub ttl: u32,
     pub class: RRClass,
     pub rrtype: RRType,
     pub data: Vec<RecordData>,
+    // True if the data is in the generic format of RFC 3597, written
+    // with an unquoted \#. The first field is then "#".
+    pub generic: bool,
+    // False if the TTL was not given in the record
+    pub ttl_explicit: bool,
+    // Source location. Boxed to keep the record small when disabled.
+    pub span: Option<Box<Span>>,
 }
 
 impl PartialEq for Record {
@@ -131,6 +314,7 @@ impl PartialEq for Record {
 	    self.ttl != other.ttl ||
 	    self.class != other.class ||
 	    self.rrtype != other.rrtype ||
+	    self.generic != other.generic ||
 	    self.data.len() != other.data.len() {
 		return false;
 	    }
@@ -147,11 +331,35 @@ impl PartialEq for Record {
 }
 
 impl Display for Record {
+    // The alternate form ("{:#}") shows the TTL with units
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
-	write!(f, "{} {} {} {}", self.name, self.ttl, self.class, self.rrtype)?;
+	if f.alternate() {
+	    write!(f, "{} {} {} {}", self.name, Ttl(self.ttl), self.class,
+		   self.rrtype)?;
+	}
+	else {
+	    write!(f, "{} {} {} {}", self.name, self.ttl, self.class,
+		   self.rrtype)?;
+	}
 
-	for d in &self.data {
-	    write!(f, " {}", d)?
+	for (i, d) in self.data.iter().enumerate() {
+	    if i == 0 && self.generic {
+		write!(f, " \\#")?;
+	    }
+	    else if self.name_field(i) {
+		// Names are kept in presentation format. Names kept as
+		// written may still have non-printable bytes.
+		write!(f, " ")?;
+		for &b in d.as_bytes() {
+		    match b {
+			0x21..=0x7e => write!(f, "{}", b as char)?,
+			_ => write!(f, "\\{:03}", b)?,
+		    }
+		}
+	    }
+	    else {
+		write!(f, " {}", d)?;
+	    }
 	}
 
 	Ok(())
@@ -159,13 +367,23 @@ impl Display for Record {
 }
 
 impl Record {
-    pub fn new(name: &str, ttl: u32, class: RRClass , rrtype: RRType) -> Self {
+    // True if the data field at the index is a domain name, kept in
+    // presentation format
+    pub fn name_field(&self, index: usize) -> bool {
+	return rdata_name_field(self.rrtype, index, &self.data, self.generic);
+    }
+
+    pub fn new(name: &DomainName, ttl: u32, class: RRClass , rrtype: RRType)
+	       -> Self {
 	Self {
-	    name: name.to_string(),
+	    name: name.clone(),
 	    ttl: ttl,
 	    class: class,
 	    rrtype: rrtype,
 	    data: Default::default(),
+	    generic: false,
+	    ttl_explicit: true,
+	    span: None,
 	}
     }
 
@@ -184,22 +402,85 @@ enum ParserState {
     QString,
 }
 
-pub struct ZoneParser<'a> {
-    bufreader: BufReader<&'a File>,
+// An included zonefile. The position and names of the including file
+// are kept, so that they can be restored when the included file ends.
+struct Include {
+    bufreader: BufReader<File>,
+    path: PathBuf,
+    canonical_path: PathBuf,
+    parent_line_no: usize,
+    parent_offset: usize,
+    parent_origin: Option<DomainName>,
+    parent_name: DomainName,
+}
+
+// Default limit for nested $INCLUDE directives
+const MAX_INCLUDE_DEPTH: usize = 16;
+
+pub struct ZoneParser<R> {
+    bufreader: R,
     line_no: usize,
+    // Byte offset of the current and the next line
+    line_offset: usize,
+    offset: usize,
+    // Path of the zonefile, if known
+    path: Option<PathBuf>,
+
+    // Stack of included files
+    includes: Vec<Include>,
+    // Base directory for relative include paths
+    include_dir: Option<PathBuf>,
+    // Maximum include depth
+    max_include_depth: usize,
+    // Make domain names in the record data absolute
+    absolute_rdata_names: bool,
+    // Which names to lowercase
+    case_policy: CasePolicy,
+    // Convert UTF-8 names to A-labels
+    idna: bool,
+    // Warn about names which are not valid host names
+    hostname_checks: bool,
+    // Add spans to the records
+    spans: bool,
+    // File name for the spans
+    span_file: Option<Arc<Path>>,
+    // Line number and offset of the start of the current record
+    rec_start: (usize, usize),
+    // Active $GENERATE directive
+    generate: Option<Generate>,
+    // Continue with the next record after errors
+    recover: bool,
+    // Skipping the rest of a bad record
+    resync: bool,
+    // Inside a quoted string while skipping
+    resync_quoted: bool,
 
     // Buffer for quoted strings
-    quoted_buf: String,
+    quoted_buf: Vec<u8>,
     // Buffer for variable name
     directive_buf: String,
+    // Directive arguments with their line positions
+    directive_args: Vec<(String, usize)>,
     // Name of current record
-    name: String,
-    // Name of zone apex
-    origin: String,
-    // Default ttl
-    default_ttl: u32,
-    // Current ttl
+    name: DomainName,
+    // Current origin, if known
+    origin: Option<DomainName>,
+    // Error in the origin given to the constructor
+    origin_error: Option<ParseError>,
+    // Default ttl from $TTL
+    default_ttl: Option<u32>,
+    // Last explicit ttl
     ttl: u32,
+    // The current record has an explicit ttl
+    ttl_explicit: bool,
+    // How to find the ttl when it is not given
+    ttl_policy: TtlPolicy,
+    // Minimum field of the last SOA record
+    soa_minimum: Option<u32>,
+    // A warning about the missing $TTL has been given
+    ttl_warned: bool,
+    // Warnings not yet taken by the caller
+    warnings: Vec<ParseError>,
     // Current class
     class: RRClass,
     // Current type
@@ -213,27 +494,64 @@ pub struct ZoneParser<'a> {
 
     rrtype_hash: HashMap<String, RRType>,
     rrclass_hash: HashMap<String, RRClass>,
-    rrtype_bm_hash: HashMap<String, (u8, u128, u128)>,
 }
 
-impl<'a> Iterator for ZoneParser<'a> {
-    type Item = Result<Record, String>;
+impl<R: BufRead> Iterator for ZoneParser<R> {
+    type Item = Result<Record, ParseError>;
 
     fn next(&mut self) -> Option<Self::Item> {
-	self.state = ParserState::Init;
-	if self.default_ttl != 0 {
-	    self.ttl = self.default_ttl;
+	if let Some(e) = self.origin_error.take() {
+	    // Nothing can be parsed without a valid origin
+	    self.end_of_stream = true;
+	    return Some(Err(e));
 	}
 
+	self.state = ParserState::Init;
+	self.ttl_explicit = false;
+
         let mut rec: Option<Record> = None;
 
 	while !self.end_of_stream {
+	    if let Some(generate) = self.generate.as_mut() {
+		// Expand the next line of the $GENERATE directive
+		match generate.next() {
+		    Some(Ok(line)) => {
+			if let Err(e) = self.parse_or_resync(line.as_bytes(),
+							     &mut rec) {
+			    // Recovery continues with the next line
+			    if !self.recover {
+				self.generate = None;
+			    }
+			    return Some(Err(e));
+			}
+
+			if let Some(r) = rec.take() {
+			    return Some(self.finish_record(r));
+			}
+
+			continue;
+		    },
+		    Some(Err(e)) => {
+			if !self.recover {
+			    self.generate = None;
+			}
+			return Some(Err(self.error(ErrorKind::BadDirective, &e,
+						   "$generate", None)));
+		    },
+		    None => {
+			self.generate = None;
+		    },
+		}
+	    }
+
 	    if let Err(e) = self.parse_line(&mut rec) {
                 return Some(Err(e));
             }
 
-            if rec.is_some() && self.b_count == 0 {
-		return Some(Ok(rec.unwrap()));
+            if self.b_count == 0 {
+                if let Some(r) = rec.take() {
+                    return Some(self.finish_record(r));
+                }
             }
 	}
 
@@ -241,11 +559,32 @@ impl<'a> Iterator for ZoneParser<'a> {
     }
 }
 
-impl<'a> ZoneParser<'a> {
-    pub fn new(file: &'a File, origin: &str) -> Self {
-	let buf = BufReader::new(file);
+impl ZoneParser<BufReader<File>> {
+    // Open a zonefile and parse it
+    pub fn from_path<P: AsRef<Path>>(path: P, origin: &str)
+                                     -> std::io::Result<Self> {
+        let file = File::open(&path)?;
+        let mut parser = Self::new(BufReader::new(file), origin);
+        parser.path = Some(path.as_ref().to_path_buf());
+        parser.span_file = Some(Arc::from(path.as_ref()));
+        return Ok(parser);
+    }
+}
 
-        // Build some lookup tables for classes, types and type bitmaps
+impl<'a> ZoneParser<&'a [u8]> {
+    // Parse a zone held in memory
+    pub fn from_str(zone: &'a str, origin: &str) -> Self {
+        return Self::new(zone.as_bytes(), origin);
+    }
+
+    pub fn from_bytes(zone: &'a [u8], origin: &str) -> Self {
+        return Self::new(zone, origin);
+    }
+}
+
+impl<R: BufRead> ZoneParser<R> {
+    pub fn new(reader: R, origin: &str) -> Self {
+        // Build some lookup tables for classes and types
 	let mut classes = HashMap::new();
 	
 	for c in RRClass::values() {
@@ -259,47 +598,51 @@ impl<'a> ZoneParser<'a> {
 	    types.insert(t_str, t);
         }
 
-        let mut bm_hash = HashMap::new();
-
-        for t in RRType::values() {
-            if t == RRType::None {
-                continue;
-            }
-
-            let t_str = format!("{:?}", t).to_lowercase();
-            let t_disc = t.discriminant();
-            let window_block = (t_disc >> 8) as u8;
-            let bitpos = t_disc & 0xff;
-            let bm1: u128;
-            let bm2: u128;
-            if bitpos < 128 {
-                bm1 = 1 << (127 - bitpos);
-                bm2 = 0;
-            }
-            else {
-                bm1 = 0;
-                bm2 = 1 << (255 - bitpos);
-            }
-            bm_hash.insert(t_str, (window_block, bm1, bm2));
-        }
-
-        // Tolerate origin with or without ending dot
-        let mut origin_muted = origin.to_string();
-        if !origin_muted.ends_with('.') {
-            origin_muted.push('.');
-        }
+        // Tolerate origin with or without ending dot. An empty origin
+        // means that the zonefile must set it.
+        let (origin, origin_error) = match origin.parse::<DomainName>() {
+            _ if origin.is_empty() => (None, None),
+            Ok(o) => (Some(o.append(&DomainName::root())), None),
+            Err(e) => (None,
+                       Some(ParseError::new(ErrorKind::BadName, &e, origin))),
+        };
 
 	Self {
 	    // Input text with position counters
-	    bufreader: buf,
+	    bufreader: reader,
 	    line_no: 0,
+	    line_offset: 0,
+	    offset: 0,
+	    path: None,
+	    // Included files
+	    includes: vec!(),
+	    include_dir: None,
+	    max_include_depth: MAX_INCLUDE_DEPTH,
+	    absolute_rdata_names: true,
+	    case_policy: Default::default(),
+	    idna: false,
+	    hostname_checks: false,
+	    spans: false,
+	    span_file: None,
+	    rec_start: (0, 0),
+	    generate: None,
+	    recover: false,
+	    resync: false,
+	    resync_quoted: false,
 	    // Parser intermediary values
-	    quoted_buf: "".to_string(),
+	    quoted_buf: vec!(),
 	    directive_buf: "".to_string(),
-	    name: "".to_string(),
-	    origin: origin_muted,
-	    default_ttl: 0,
+	    directive_args: vec!(),
+	    name: DomainName::root(),
+	    origin: origin,
+	    origin_error: origin_error,
+	    default_ttl: None,
 	    ttl: 0,
+	    ttl_explicit: false,
+	    ttl_policy: Default::default(),
+	    soa_minimum: None,
+	    ttl_warned: false,
+	    warnings: vec!(),
 	    class: Default::default(),
 	    rrtype: Default::default(),
 	    b_count: 0,
@@ -308,381 +651,922 @@ impl<'a> ZoneParser<'a> {
 
 	    rrclass_hash: classes,
 	    rrtype_hash: types,
-            rrtype_bm_hash: bm_hash,
 	}
     }
 
-    pub fn rrclass_from_str(&self, rrclass_str: &str) -> RRClass {
-        return *self.rrclass_hash.get(&rrclass_str.to_lowercase()).unwrap();
+    // Set the directory which relative $INCLUDE paths are resolved
+    // against. By default, they are relative to the including file.
+    pub fn set_include_dir<P: AsRef<Path>>(&mut self, dir: P) {
+        self.include_dir = Some(dir.as_ref().to_path_buf());
+    }
+
+    // Set the maximum nesting depth of $INCLUDE directives
+    pub fn set_max_include_depth(&mut self, depth: usize) {
+        self.max_include_depth = depth;
+    }
+
+    // Enable error recovery. After an error, the rest of the bad record
+    // is skipped and parsing continues with the next record, so that
+    // all errors in a zone can be found in one pass. By default, the
+    // parser state is undefined after an error.
+    pub fn set_error_recovery(&mut self, recover: bool) {
+        self.recover = recover;
+    }
+
+    // Set how the TTL of records without a TTL is found when there is
+    // no $TTL directive
+    pub fn set_ttl_policy(&mut self, policy: TtlPolicy) {
+        self.ttl_policy = policy;
+    }
+
+    // Take the warnings found so far
+    pub fn take_warnings(&mut self) -> Vec<ParseError> {
+        return std::mem::take(&mut self.warnings);
+    }
+
+    // Make relative domain names in the record data absolute, which is
+    // the default. If disabled, the names are kept as written.
+    pub fn set_absolute_rdata_names(&mut self, absolute: bool) {
+        self.absolute_rdata_names = absolute;
+    }
+
+    // Set which names are lowercased. By default, owner names and
+    // $ORIGIN are lowercased, and names in the record data are not.
+    pub fn set_case_policy(&mut self, policy: CasePolicy) {
+        self.case_policy = policy;
     }
 
-    pub fn rrtype_from_str(&self, rrtype_str: &str) -> Result<RRType, String> {
+    // Accept domain names in UTF-8 and convert them to A-labels, with
+    // IDNA2008 validation. Invalid IDNs, including bad "xn--" labels,
+    // are errors. Disabled by default, where labels are opaque bytes.
+    pub fn set_idna(&mut self, idna: bool) {
+        self.idna = idna;
+    }
+
+    // Check the host name rules where the RFCs require them: the owner
+    // names of A and AAAA records, and the targets of MX and NS records.
+    // The owner names of SRV, TLSA and DKIM records may have leading "_"
+    // labels. Bad names are reported as warnings. Disabled by default.
+    pub fn set_hostname_checks(&mut self, checks: bool) {
+        self.hostname_checks = checks;
+    }
+
+    // Enable or disable the source locations of the records. They are
+    // disabled by default.
+    pub fn set_spans(&mut self, spans: bool) {
+        self.spans = spans;
+    }
+
+    pub fn rrclass_from_str(&self, rrclass_str: &str)
+                            -> Result<RRClass, ParseError> {
+        match self.rrclass_hash.get(&rrclass_str.to_lowercase()) {
+            Some(rrclass) => return Ok(*rrclass),
+            None => return Err(ParseError::new(
+                ErrorKind::UnknownClass,
+                &format!("Unknown class {}", rrclass_str), rrclass_str)),
+        }
+    }
+
+    pub fn rrtype_from_str(&self, rrtype_str: &str)
+                           -> Result<RRType, ParseError> {
         let lcstr = rrtype_str.to_lowercase();
 
         if let Some(rrtype) = self.rrtype_hash.get(&lcstr) {
             return Ok(*rrtype);
         }
-        else if lcstr.starts_with("type") {
-            let rrtype = RRType::from_discriminant(
-                lcstr[4..].parse().expect(&format!(
-                    "Unknown type {}", rrtype_str)));
-            return Ok(rrtype);
+        else if let Some(t_disc) = lcstr.strip_prefix("type")
+            .and_then(number) {
+            return Ok(RRType::from_discriminant(t_disc));
         }
         else {
-            return Err(format!("Unknown type {}", rrtype_str));
+            return Err(ParseError::new(
+                ErrorKind::UnknownType,
+                &format!("Unknown type {}", rrtype_str), rrtype_str));
         }
     }
 
     // RRType bitmap for NSEC and NSEC3 records
     pub fn rrtype_bm_from_str(&self, rrtype_str: &str)
-                              -> Result<(u8, u128, u128), String> {
-        let lcstr = rrtype_str.to_lowercase();
+                              -> Result<(u8, u128, u128), ParseError> {
+        return rrtype_bitmap(rrtype_str).ok_or_else(
+            || ParseError::new(ErrorKind::UnknownType,
+                               &format!("Unknown type {}", rrtype_str),
+                               rrtype_str));
+    }
 
-        if let Some(bm) = self.rrtype_bm_hash.get(&lcstr) {
-            return Ok(*bm);
-        }
-        else if lcstr.starts_with("type") {
-            let t_disc: u16 = lcstr[4..].parse().expect(&format!(
-                "Unknown type {}", rrtype_str));
-            let window_block = (t_disc >> 8) as u8;
-            let bitpos = t_disc & 0xff;
-            let bm1: u128;
-            let bm2: u128;
-            if bitpos < 128 {
-                bm1 = 1 << (127 - bitpos);
-                bm2 = 0;
-            }
-            else {
-                bm1 = 0;
-                bm2 = 1 << (255 - bitpos);
+    // Unescape an unquoted word into self.quoted_buf. Quotes must be
+    // escaped, except around a value after "=" with key_value.
+    fn unescape_word(&mut self, word: &[u8], pos: usize, key_value: bool)
+                     -> Result<(), ParseError> {
+        let mut quoted = false;
+        let mut i = 0;
+
+        while i < word.len() {
+            match word[i] {
+                b'"' if quoted && i + 1 == word.len() => {
+                    quoted = false;
+                    i += 1;
+                },
+                b'"' if key_value && !quoted && i > 0 &&
+                    word[i - 1] == b'=' => {
+                    quoted = true;
+                    i += 1;
+                },
+                b'"' => {
+                    break;
+                },
+                b'\\' => {
+                    let (value, len) = unescape_at(word, i).ok_or_else(
+                        || self.error(ErrorKind::BadEscaping, "Bad escaping",
+                                      &word.to_str_lossy(), Some(pos)))?;
+                    self.quoted_buf.push(value);
+                    i += len;
+                },
+                b => {
+                    self.quoted_buf.push(b);
+                    i += 1;
+                },
             }
-            return Ok((window_block, bm1, bm2));
         }
-        else {
-            return Err(format!("Unknown type {}", rrtype_str));
+
+        if quoted || i < word.len() {
+            return Err(self.error(ErrorKind::BadQuoting, "Bad quoting",
+                                  &word.to_str_lossy(), Some(pos)));
         }
+
+        return Ok(());
     }
 
-    // Stores the unescaped data in self.quoted_buf. Return true if
-    // part ends with an unescaped quote.
-    fn unescape_quoted_data(&mut self, part: &[u8]) -> Result<bool, String> {
-        // Look up instances of '\' and '"'
-        let mut esc_end = false;
-        let mut quote_end = false;
+    // Unescape a quoted string into self.quoted_buf, starting after the
+    // opening quote or at the start of a continued line. Returns the
+    // position after the closing quote, or None if the string continues
+    // on the next line. The line ending is kept as a single newline.
+    fn unescape_quoted(&mut self, bytes: &[u8], start: usize, pos: usize)
+                       -> Result<Option<usize>, ParseError> {
+        let mut i = start;
+
+        while i < bytes.len() {
+            match bytes[i] {
+                b'\\' => {
+                    let (value, len) = unescape_at(bytes, i).ok_or_else(
+                        || self.error(ErrorKind::BadEscaping, "Bad escaping",
+                                      &bytes[pos..].to_str_lossy(),
+                                      Some(pos)))?;
+                    self.quoted_buf.push(value);
+                    i += len;
+                },
+                b'"' => {
+                    // End quote. Only a delimiter may follow.
+                    if bytes.get(i + 1).is_some_and(|b| !is_delimiter(*b)) {
+                        return Err(self.error(
+                            ErrorKind::BadQuoting, "Bad quoting",
+                            &bytes[pos..].to_str_lossy(), Some(pos)));
+                    }
 
-        for p in part.split_inclusive(|&b| b == b'\\' || b == b'"') {
-            let plen = p.len();
+                    return Ok(Some(i + 1));
+                },
+                b'\r' if bytes[i + 1..] == *b"\n" => {
+                    i += 1;
+                },
+                b => {
+                    self.quoted_buf.push(b);
+                    i += 1;
+                },
+            }
+        }
 
-            if quote_end {
-                // '" ', '"\n': End of quote part
-                if plen == 1 && (p[0] == b' ' || p[0] == b'\n') {
-                    continue;
-                }
+        return Ok(None);
+    }
 
-                // '"whatever' -> parse error
-                return Err(format!("Bad quoting on line {}", self.line_no));
-            }
+    // At the end of an included file, continue with the including
+    // file. False if not in an included file.
+    fn end_include(&mut self) -> bool {
+	let include = match self.includes.pop() {
+	    Some(include) => include,
+	    None => return false,
+	};
+
+	self.line_no = include.parent_line_no;
+	self.offset = include.parent_offset;
+	self.origin = include.parent_origin;
+	self.name = include.parent_name;
+	self.span_file = self.current_path().map(Arc::from);
+	return true;
+    }
 
-            if esc_end {
-                // Previous character is '\':
-                if p[0] == b'"' {
-                    // Escaped '"': push '"' into buffer
-                    self.quoted_buf.push('"');
-                    esc_end = false;
-                    continue;
-                }
+    fn parse_line(&mut self, rec: &mut Option<Record>)
+                  -> Result<(), ParseError> {
+	let mut line: Vec<u8> = vec!();
+	let read = match self.includes.last_mut() {
+	    Some(include) => include.bufreader.read_until(b'\n', &mut line),
+	    None => self.bufreader.read_until(b'\n', &mut line),
+	};
+	let len = match read {
+	    Ok(len) => len,
+	    Err(e) => {
+		// The rest of the file can't be read
+		let err = self.io_error("Error reading zonefile", "", e);
+		if !self.end_include() {
+		    self.end_of_stream = true;
+		}
+		return Err(err);
+	    },
+	};
+	if len == 0 {
+	    if self.end_include() {
+		return Ok(());
+	    }
 
-                if p[0] == b'\\' {
-                    // Escaped '\': push '\' into buffer
-                    self.quoted_buf.push('\\');
-                    esc_end = false;
-                    continue;
-                }
+	    self.end_of_stream = true;
 
-                let mut remaining = 0;
-                static OCTAL1: std::ops::Range<u8> = b'0'..b'4';
-                static OCTAL2: std::ops::Range<u8> = b'0'..b'8';
-
-                if plen >= 3 {
-                    if OCTAL1.contains(&p[0]) &&
-                       OCTAL2.contains(&p[1]) &&
-                       OCTAL2.contains(&p[2]) {
-                        let i = 64*(p[0] - b'0') + 8*(p[1] - b'0') +
-                            (p[2] - b'0');
-                        // Occurence of escaped octal number representation
-                        self.quoted_buf.push(i as char);
-                        remaining = 3;
-                        if remaining == plen {
-                            quote_end = false;
-                            esc_end = false;
-                            continue;
-                        }
-                    }
-                }
+	    if self.state == ParserState::QString || self.resync_quoted {
+		self.resync_quoted = false;
+		return Err(self.error(ErrorKind::BadQuoting,
+				      "Unexpected end of file in quoted string",
+				      "", None));
+	    }
 
-                if p[plen - 1] == b' ' {
-                    // Escaped first char and space ending:
-                    //   push part with space ending
-                    let s = format!("{} ", &p[0..plen - 1].escape_bytes());
-		    self.quoted_buf.push_str(&s);
-                    esc_end = false;
-                    continue;
-                }
+	    if self.b_count > 0 {
+		return Err(self.error(ErrorKind::UnbalancedParentheses,
+				      "Unexpected end of file in parentheses",
+				      "", None));
+	    }
 
-                if p[plen - 1] == b'"' {
-                    // Escaped first char and end quote:
-                    //   push part and remember end quote
-		    self.quoted_buf.push_str(
-                        &p[remaining..plen - 1].escape_bytes().to_string());
-                    quote_end = true;
-                    esc_end = false;
-                    continue;
-                }
+	    return Ok(());
+	}
+	self.line_no += 1;
+	self.line_offset = self.offset;
+	self.offset += len;
 
-                if p[plen - 1] == b'\\' {
-                    // Escaped first char and '\' ending:
-                    //   push part and remember '\' ending
-		    self.quoted_buf.push_str(
-                        &p[remaining..plen - 1].escape_bytes().to_string());
-                    continue;
-                }
+	// Skip a UTF-8 byte order mark at the start of a file
+	let bytes = match self.line_no {
+	    1 => line.strip_prefix(b"\xef\xbb\xbf").unwrap_or(&line),
+	    _ => &line,
+	};
 
-                // Escaped first char:
-                //   push part
-		self.quoted_buf.push_str(
-                    &p[remaining..plen].escape_bytes().to_string());
-                esc_end = false;
-                continue;
-            }
+	return self.parse_or_resync(bytes, rec);
+    }
 
-            // Previous character is not '\'
-            if p[0] == b'"' {
-                // End quote
-                quote_end = true;
-                continue;
-            }
+    // Parse a line, or skip it if recovering from an error. On errors in
+    // recovery mode, the line is scanned again to find the end of the
+    // bad record.
+    fn parse_or_resync(&mut self, bytes: &[u8], rec: &mut Option<Record>)
+		       -> Result<(), ParseError> {
+	if self.resync {
+	    self.resync_bytes(bytes);
+	    return Ok(());
+	}
 
-            if p[0] == b'\\' {
-                // Single escape character
-                esc_end = true;
-                continue;
-            }
+	let b_count = self.b_count;
+	let quoted = self.state == ParserState::QString;
 
-            if p[plen - 1] == b' ' {
-                // Part with space ending
-                let s = format!("{} ", &p[0..plen - 1].escape_bytes());
-		self.quoted_buf.push_str(&s);
-                continue;
-            }
+	let result = self.parse_bytes(bytes, rec);
 
-            if p[plen - 1] == b'"' {
-                // Part with end quote
-		self.quoted_buf.push_str(
-                    &p[0..plen - 1].escape_bytes().to_string());
-                quote_end = true;
-                continue;
-            }
+	if result.is_err() && self.recover {
+	    self.b_count = b_count;
+	    self.resync_quoted = quoted;
+	    self.resync_bytes(bytes);
+	    self.directive_args.clear();
+	    self.state = ParserState::Init;
+	}
 
-            if p[plen - 1] == b'\\' {
-                // Part ending with escape character
-		self.quoted_buf.push_str(
-                    &p[0..plen - 1].escape_bytes().to_string());
-                esc_end = true;
-                continue;
-            }
+	return result;
+    }
 
-            // Clean part
-	    self.quoted_buf.push_str(&p.escape_bytes().to_string());
-        }
+    // Skip a line of a bad record. Only quotes and parentheses are
+    // tracked. The record ends at the first line end outside parentheses.
+    fn resync_bytes(&mut self, bytes: &[u8]) {
+	let mut i = 0;
+	// A quote only starts a string at the start of a token
+	let mut token_start = true;
+
+	while i < bytes.len() {
+	    let b = bytes[i];
+
+	    if self.resync_quoted {
+		match b {
+		    b'\\' => i += 1,
+		    b'"' => self.resync_quoted = false,
+		    _ => { },
+		}
+	    }
+	    else {
+		match b {
+		    b'\\' => i += 1,
+		    b'"' if token_start => self.resync_quoted = true,
+		    b';' => break,
+		    b'(' => self.b_count = self.b_count.saturating_add(1),
+		    b')' => self.b_count = self.b_count.saturating_sub(1),
+		    _ => { },
+		}
+	    }
 
-        if esc_end {
-            return Err(format!("Bad escaping on line {}", self.line_no));
-        }
+	    token_start = b" \t\r\n()".contains(&b);
+	    i += 1;
+	}
 
-        return Ok(quote_end);
+	self.resync = self.b_count > 0;
+	if !self.resync {
+	    self.resync_quoted = false;
+	}
     }
 
-    fn parse_line(&mut self, rec: &mut Option<Record>) -> Result<(), String> {
-	let mut line: String = "".to_string();
-	let len = self.bufreader.read_line(&mut line).
-	    expect("Error reading zonefile");
-	if len == 0 {
-	    self.end_of_stream = true;
-	    return Ok(());
+    // Split a line into tokens. Unquoted tokens end at whitespace,
+    // parentheses and comments, unless escaped. Quoted strings may
+    // contain all of these, and continue on the next line inside
+    // parentheses.
+    fn parse_bytes(&mut self, bytes: &[u8], rec: &mut Option<Record>)
+		   -> Result<(), ParseError> {
+	let mut i = 0;
+
+	if self.state == ParserState::QString {
+	    // Continue the quoted string from the last line
+	    match self.unescape_quoted(bytes, 0, 0)? {
+		Some(end) => {
+		    self.push_quoted(rec);
+		    i = end;
+		},
+		None => i = bytes.len(),
+	    }
 	}
-	let bytes = line.as_bytes();
-	let mut pos = 0;
-	self.line_no += 1;
 
-	for part in bytes.split_inclusive(
-	    |&b| b == b' ' || b == b'\t' || b == b'\n' ||
-		 b == b'(' || b == b')') {
-	    let plen = part.len();
-	    let mut wlen = plen;
+	while i < bytes.len() {
+	    let pos = i;
 
-	    if part[0] == b';' && self.state != ParserState::QString {
-		// Comment. Skip the rest of the line
-		return Ok(());
-	    }
-	    
-	    // Check end character
-	    match part[plen - 1] {
-		b' ' | b'\t' | b'\n' => {
-		    wlen -= 1;
+	    match bytes[i] {
+		b' ' | b'\t' | b'\r' | b'\n' => {
+		    i += 1;
+		},
+		b';' => {
+		    // Comment. Skip the rest of the line
+		    break;
 		},
 		b'(' => {
-		    self.b_count += 1;
-		    wlen -= 1;
+		    self.b_count = self.b_count.checked_add(1).ok_or_else(
+			|| self.error(ErrorKind::UnbalancedParentheses,
+				      "Too many open parentheses", "(",
+				      Some(pos)))?;
+		    i += 1;
 		},
 		b')' => {
-		    self.b_count -= 1;
-		    wlen -= 1;
+		    self.b_count = self.b_count.checked_sub(1).ok_or_else(
+			|| self.error(ErrorKind::UnbalancedParentheses,
+				      "Unbalanced closing parenthesis", ")",
+				      Some(pos)))?;
+		    i += 1;
+		},
+		b'"' => {
+		    self.quoted_buf.clear();
+		    let complete = match self.unescape_quoted(bytes, i + 1, pos)? {
+			Some(end) => {
+			    i = end;
+			    true
+			},
+			None => {
+			    i = bytes.len();
+			    false
+			},
+		    };
+		    self.parse_quoted(complete, &bytes[pos..i], pos, rec)?;
+		},
+		_ => {
+		    i = token_end(bytes, i, self.key_values(rec));
+		    self.parse_word(&bytes[pos..i], pos, rec)?;
 		},
-		_ => { },
 	    }
+	}
 
-	    if wlen == 0 && (part[0] == b'\n' || self.state != ParserState::Init) {
-		// Single whitespace, bracket or single newline. Skip it
-		continue;
-	    }
+	if self.state == ParserState::Directive {
+	    self.state = ParserState::Init;
+	    self.run_directive()?;
+	}
 
-	    match self.state {
-		ParserState::Init => {
-		    let word = part[0..wlen].escape_bytes().to_string()
-			.to_lowercase();
-		    // Parse the common part of the record
-		    if pos == 0 && self.b_count == 0 {
-			// Start of record. Expect word to be the domain name
-			if word.starts_with('$') {
-			    // Lines starting with $ is a directive
-			    self.directive_buf = word;
-			    self.state = ParserState::Directive;
-			}
-			else {
-			    // If the name is empty, use the name from
-			    // the last record
-			    if wlen > 0 {
-				self.name = self.absolute_name(&word);
-			    }
+	if self.state == ParserState::QString && self.b_count == 0 {
+	    // Quoted strings may only span lines within parentheses
+	    return Err(self.error(ErrorKind::BadQuoting,
+				  "Unterminated quoted string", "", None));
+	}
 
-			    self.state = ParserState::Common;
-			}
-		    }
-		},
-		ParserState::Common => {
-		    let word = part[0..wlen].escape_bytes().to_string()
-			.to_lowercase();
-		    if let Some(class) = self.rrclass_hash.get(&word) {
-			// Found class.
-			self.class = *class;
-		    }
-		    else if let Some(rrtype) = self.rrtype_hash.get(&word) {
-			// Found type. Create a record
-			self.rrtype = *rrtype;
-			self.state = ParserState::Data;
-			let _ = rec.insert(
-			    Record::new(&self.name, self.ttl,
-					self.class, self.rrtype));
-		    }
-                    else if word.starts_with("type") {
-                        // TYPENNN syntax
-                        let rrvalue: u16 = word[4..].parse().expect(&format!(
-                            "Expected TYPE<NUM> on line {} pos {}",
-                            self.line_no, pos));
-                        self.rrtype = RRType::from_discriminant(rrvalue);
-                        self.state = ParserState::Data;
-			let _ = rec.insert(
-			    Record::new(&self.name, self.ttl,
-					self.class, self.rrtype));
-                    }
-		    else {
-			// Expect TTL
-			self.ttl = word.parse().or(
-                            Err(format!("Unexpected content on line {} pos {}",
-                                        self.line_no, pos)))?;
-		    }
-		},
-		ParserState::Directive => {
-		    // Parsing a directive line.
-		    let value = part[0..wlen].escape_bytes().to_string().
-			to_lowercase();
-		    if self.directive_buf == "$ttl" {
-			self.default_ttl = value.parse().or(
-                            Err(format!("Unexpected content on line {} pos {}",
-                                        self.line_no, pos)))?;
-		    }
-		    else if self.directive_buf == "$origin" {
-			self.origin = value;
-		    }
-		    else {
-                        return Err(format!("Unknown directive {}",
-                                           self.directive_buf));
-		    }
-		    self.state = ParserState::Init;
-		},
-		ParserState::Data => {
-		    if part[0] == b'"' {
-			// Start of quoted string.
-                        self.quoted_buf.clear();
-                        let end_quote = self.unescape_quoted_data(&part[1..])?;
-                        if end_quote {
-			    // Got end quote.
-			    rec.as_mut().unwrap().push_data(
-			        RecordData::new(&self.quoted_buf));
-                        }
-                        else {
-			    self.state = ParserState::QString;
-			}
-		    }
-		    else {
-			// Unquoted data
-                        self.quoted_buf.clear();
-                        let end_quote = self.unescape_quoted_data(
-                            &part[0..wlen])?;
-                        if end_quote {
-                            self.quoted_buf.push('"');
-                        }
-			rec.as_mut().unwrap().push_data(
-			    RecordData::new(&self.quoted_buf));
-		    }
-		},
-		ParserState::QString => {
-                    let end_quote = self.unescape_quoted_data(part)?;
-                    if end_quote {
-			// Got end quote
-			rec.as_mut().unwrap().push_data(
-			    RecordData::new(&self.quoted_buf));
-			self.state = ParserState::Data;
+        return Ok(());
+    }
+
+    // True in the data of types with key="value" parameters
+    fn key_values(&self, rec: &Option<Record>) -> bool {
+	return self.state == ParserState::Data && rec.as_ref().is_some_and(
+	    |r| r.rrtype == RRType::SVCB || r.rrtype == RRType::HTTPS);
+    }
+
+    // Add a complete quoted string to the record
+    fn push_quoted(&mut self, rec: &mut Option<Record>) {
+	if let Some(r) = rec.as_mut() {
+	    r.push_data(RecordData::from_bytes(&self.quoted_buf));
+	}
+	self.state = ParserState::Data;
+    }
+
+    // Handle a quoted string. The unescaped string is in
+    // self.quoted_buf.
+    fn parse_quoted(&mut self, complete: bool, token: &[u8], pos: usize,
+		    rec: &mut Option<Record>) -> Result<(), ParseError> {
+	match self.state {
+	    ParserState::Data if complete => {
+		self.push_quoted(rec);
+	    },
+	    ParserState::Data => {
+		self.state = ParserState::QString;
+	    },
+	    ParserState::Directive if complete => {
+		// Directive arguments are kept as written
+		self.directive_args.push((token.to_str_lossy().to_string(), pos));
+	    },
+	    ParserState::Directive => {
+		return Err(self.error(ErrorKind::BadQuoting,
+				      "Unterminated quoted string",
+				      &token.to_str_lossy(), Some(pos)));
+	    },
+	    _ => {
+		// Names, TTLs, classes and types can't be quoted
+		return Err(self.error(ErrorKind::BadQuoting,
+				      "Unexpected quoted string",
+				      &token.to_str_lossy(), Some(pos)));
+	    },
+	}
+
+	return Ok(());
+    }
+
+    // Handle an unquoted token, with escapes kept
+    fn parse_word(&mut self, word: &[u8], pos: usize,
+		  rec: &mut Option<Record>) -> Result<(), ParseError> {
+	if self.state == ParserState::Init {
+	    // Start of record
+	    self.rec_start = (self.line_no, self.line_offset);
+	    self.state = ParserState::Common;
+
+	    if pos == 0 && word.starts_with(b"$") {
+		// Lines starting with $ is a directive
+		self.directive_buf = word.to_str_lossy().to_lowercase();
+		self.state = ParserState::Directive;
+		return Ok(());
+	    }
+	    else if pos == 0 {
+		// Owner name. A line starting with whitespace has the
+		// name from the last record.
+		self.name = self.parse_name(
+		    word, Some(pos), self.case_policy.owners())?;
+		return Ok(());
+	    }
+	}
+
+	match self.state {
+	    ParserState::Common => {
+		let word = word.to_str_lossy().to_lowercase();
+		if let Some(class) = self.rrclass_hash.get(&word) {
+		    // Found class.
+		    self.class = *class;
+		}
+		else if let Some(rrtype) = self.rrtype_hash.get(&word) {
+		    // Found type. Create a record
+		    self.rrtype = *rrtype;
+		    self.state = ParserState::Data;
+		    let _ = rec.insert(self.new_record()?);
+		}
+                else if let Some(num) = word.strip_prefix("type") {
+                    // TYPENNN syntax
+                    let rrvalue: u16 = number(num).ok_or_else(
+                        || self.error(ErrorKind::UnknownType,
+                                       &format!("Unknown type {}", word),
+                                       &word, Some(pos)))?;
+                    self.rrtype = RRType::from_discriminant(rrvalue);
+                    self.state = ParserState::Data;
+		    let _ = rec.insert(self.new_record()?);
+                }
+		else if word.starts_with(|c: char| c.is_ascii_digit()) {
+		    // Expect TTL
+		    self.ttl = word.parse::<Ttl>().map_err(
+			|e| self.error(ErrorKind::BadTtl, &e, &word,
+				       Some(pos)))?.0;
+		    self.ttl_explicit = true;
+		}
+		else if word.starts_with("class") {
+		    return Err(self.error(
+			ErrorKind::UnknownClass,
+			&format!("Unknown class {}", word), &word,
+			Some(pos)));
+		}
+		else {
+		    return Err(self.error(
+			ErrorKind::UnknownType,
+			&format!("Unknown type {}", word), &word,
+			Some(pos)));
+		}
+	    },
+	    ParserState::Directive => {
+		// Parsing a directive line. Collect the arguments, the
+		// directive is run at the end of the line.
+		self.directive_args.push((word.to_str_lossy().to_string(), pos));
+	    },
+	    ParserState::Data => {
+		if let Some(r) = rec.as_mut().filter(
+		    |r| r.data.is_empty() && word == b"\\#") {
+		    // Generic data
+		    r.generic = true;
+		    r.push_data(RecordData::new("#"));
+		}
+		else if let Some(r) = rec.as_mut().filter(
+		    |r| rdata_name_field(r.rrtype, r.data.len(), &r.data,
+					 r.generic)) {
+		    // Domain name
+		    let lowercase = self.case_policy.rdata();
+		    let name = match self.absolute_rdata_names {
+			true => self.parse_name(word, Some(pos), lowercase)?
+			    .to_string().into_bytes(),
+			false if lowercase => word.to_ascii_lowercase(),
+			false => word.to_vec(),
+		    };
+		    r.push_data(RecordData::from_bytes(&name));
+		}
+		else {
+		    // Unquoted data
+                    self.quoted_buf.clear();
+                    self.unescape_word(word, pos, self.key_values(rec))?;
+		    if let Some(r) = rec.as_mut() {
+			r.push_data(RecordData::from_bytes(&self.quoted_buf));
 		    }
-		},
+		}
+	    },
+	    ParserState::Init | ParserState::QString => { },
+	}
+
+	return Ok(());
+    }
+
+    // Start a new record. The ttl is found from the record, the $TTL
+    // directive or the ttl policy.
+    fn new_record(&mut self) -> Result<Record, ParseError> {
+	let mut rec = Record::new(&self.name, self.ttl, self.class,
+				  self.rrtype);
+	rec.ttl_explicit = self.ttl_explicit;
+
+	if self.ttl_explicit {
+	    return Ok(rec);
+	}
+
+	if let Some(ttl) = self.default_ttl {
+	    rec.ttl = ttl;
+	    return Ok(rec);
+	}
+
+	match (self.ttl_policy, self.soa_minimum) {
+	    (TtlPolicy::Strict, _) => {
+		return Err(self.error(
+		    ErrorKind::MissingTtl,
+		    &format!("Missing TTL for {}", self.name),
+		    &self.name.to_string(), None));
+	    },
+	    (TtlPolicy::LegacySoa, Some(minimum)) => {
+		self.warn_missing_ttl("No $TTL, using the SOA minimum");
+		rec.ttl = minimum;
+	    },
+	    (TtlPolicy::LegacySoa, None) if self.rrtype == RRType::SOA => {
+		// Set to the minimum field when the record is finished
+	    },
+	    _ => {
+		// Use the last explicit ttl (RFC 1035)
+		self.warn_missing_ttl("No $TTL, using the last TTL");
+	    },
+	}
+
+	return Ok(rec);
+    }
+
+    // Warn once about records without a ttl
+    fn warn_missing_ttl(&mut self, message: &str) {
+	if !self.ttl_warned {
+	    self.ttl_warned = true;
+	    let w = self.error(ErrorKind::MissingTtl, message,
+			       &self.name.to_string(), None);
+	    self.warnings.push(w);
+	}
+    }
+
+    // Final processing of a complete record
+    fn finish_record(&mut self, mut rec: Record)
+		     -> Result<Record, ParseError> {
+	if self.spans {
+	    rec.span = Some(Box::new(Span {
+		file: self.span_file.clone(),
+		start_line: self.rec_start.0,
+		end_line: self.line_no,
+		start_offset: self.rec_start.1,
+		end_offset: self.offset,
+	    }));
+	}
+
+	if rec.rrtype == RRType::SOA && !rec.generic {
+	    // The SOA timers may have TTL units. Store them in seconds.
+	    for d in rec.data.iter_mut().skip(3).take(4) {
+		let value = d.as_bytes().to_str_lossy();
+		let timer: Ttl = value.parse().map_err(
+		    |e: String| self.error(ErrorKind::BadTtl, &e, &value,
+					   None))?;
+		*d = RecordData::new(&timer.0.to_string());
 	    }
 
-            pos += plen;
+	    let minimum = rec.data.get(6)
+		.and_then(|d| d.as_str()?.parse().ok());
+	    if let Some(minimum) = minimum {
+		self.soa_minimum = Some(minimum);
+
+		if !rec.ttl_explicit && self.default_ttl.is_none() &&
+		    self.ttl_policy == TtlPolicy::LegacySoa {
+		    self.warn_missing_ttl("No $TTL, using the SOA minimum");
+		    rec.ttl = minimum;
+		}
+	    }
 	}
 
-        return Ok(());
+	if self.hostname_checks {
+	    self.check_hostnames(&rec);
+	}
+
+	return Ok(rec);
     }
 
-    pub fn absolute_name(&self, name: &str) -> String {
-	assert!(name != "");
+    // Warn about names which break the host name rules
+    fn check_hostnames(&mut self, rec: &Record) {
+	let is_dkim = || rec.rrtype == RRType::TXT &&
+	    rec.name.labels().any(|l| l.eq_ignore_ascii_case(b"_domainkey"));
+
+	let owner_ok = match rec.rrtype {
+	    RRType::A | RRType::AAAA => rec.name.is_hostname(),
+	    RRType::SRV | RRType::TLSA => rec.name.is_service_hostname(),
+	    _ if is_dkim() => rec.name.is_service_hostname(),
+	    _ => true,
+	};
+
+	if !owner_ok {
+	    let w = self.error(
+		ErrorKind::BadHostname,
+		&format!("Owner {} of {} record is not a valid host name",
+			 rec.name, rec.rrtype),
+		&rec.name.to_string(), None);
+	    self.warnings.push(w);
+	}
 
-	if name == "@" {
-	    return self.origin.clone();
+	let target = match rec.rrtype {
+	    RRType::MX => Some(1),
+	    RRType::NS => Some(0),
+	    _ => None,
+	}.filter(|i| rdata_name_field(rec.rrtype, *i, &rec.data, rec.generic))
+	    .and_then(|i| rec.data.get(i));
+
+	let target_ok = target.and_then(|t| t.as_str())
+	    .and_then(|t| t.parse::<DomainName>().ok())
+	    .is_none_or(|t| t.is_root() || t.is_hostname());
+
+	if !target_ok {
+	    let token = target.map(|t| t.to_string()).unwrap_or_default();
+	    let w = self.error(
+		ErrorKind::BadHostname,
+		&format!("Target {} of {} record is not a valid host name",
+			 token, rec.rrtype),
+		&token, None);
+	    self.warnings.push(w);
 	}
+    }
 
-	if name.ends_with('.') {
-	    return name.to_string();
+    fn run_directive(&mut self) -> Result<(), ParseError> {
+	let args = std::mem::take(&mut self.directive_args);
+
+	if args.is_empty() {
+	    return Err(self.error(
+		ErrorKind::BadDirective,
+		&format!("Missing argument to {}", self.directive_buf),
+		&self.directive_buf, None));
 	}
-	else {
-	    return format!("{}.{}", name, self.origin);
+
+	let (value, pos) = &args[0];
+
+	match self.directive_buf.as_str() {
+	    "$ttl" => {
+		self.default_ttl = Some(value.parse::<Ttl>().map_err(
+		    |e| self.error(ErrorKind::BadTtl, &e, value,
+				   Some(*pos)))?.0);
+	    },
+	    "$origin" => {
+		// Relative origins are relative to the current origin
+		self.origin = Some(self.parse_name(
+		    value.as_bytes(), Some(*pos), self.case_policy.owners())?);
+	    },
+	    "$include" => {
+		let origin = args.get(1).map(|(o, _)| o.as_str());
+		self.include(value, origin)?;
+	    },
+	    "$generate" => {
+		// Expect at least lhs, type and rhs after the range
+		if args.len() < 4 {
+		    return Err(self.error(
+			ErrorKind::BadDirective,
+			&format!("Missing argument to {}", self.directive_buf),
+			&self.directive_buf, None));
+		}
+
+		let template = args[1..].iter().map(|(a, _)| a.clone())
+		    .collect();
+		self.generate = Some(Generate::new(value, template).map_err(
+		    |e| self.error(ErrorKind::BadDirective, &e, value,
+				   Some(*pos)))?);
+	    },
+	    _ => {
+		return Err(self.error(
+		    ErrorKind::UnknownDirective,
+		    &format!("Unknown directive {}", self.directive_buf),
+		    &self.directive_buf, Some(0)));
+	    },
+	}
+
+	return Ok(());
+    }
+
+    // Path of the file currently being parsed
+    fn current_path(&self) -> Option<&Path> {
+	match self.includes.last() {
+	    Some(include) => Some(&include.path),
+	    None => self.path.as_deref(),
+	}
+    }
+
+    // Start parsing an included file. The including file is continued
+    // when the end of the included file is reached.
+    fn include(&mut self, file: &str, origin: Option<&str>)
+	       -> Result<(), ParseError> {
+	if self.includes.len() >= self.max_include_depth {
+	    return Err(self.error(
+		ErrorKind::BadInclude,
+		&format!("Include depth exceeded including {}", file),
+		file, None));
+	}
+
+	// Relative paths are resolved against the include dir, or else
+	// against the directory of the including file.
+	let mut path = PathBuf::from(file);
+	if path.is_relative() {
+	    if let Some(dir) = &self.include_dir {
+		path = dir.join(path);
+	    }
+	    else if let Some(parent) = self.current_path()
+		.and_then(|p| p.parent()) {
+		path = parent.join(path);
+	    }
 	}
+
+	let message = format!("Could not include {}", path.display());
+	let io_error = |e| self.io_error(&message, file, e);
+
+	let canonical_path = path.canonicalize().map_err(io_error)?;
+
+	let top_path = self.path.as_ref().and_then(|p| p.canonicalize().ok());
+	if top_path.as_ref() == Some(&canonical_path) ||
+	    self.includes.iter().any(|i| i.canonical_path == canonical_path) {
+	    return Err(self.error(
+		ErrorKind::BadInclude,
+		&format!("Include cycle including {}", path.display()),
+		file, None));
+	}
+
+	let f = File::open(&canonical_path).map_err(io_error)?;
+
+	// The optional origin argument applies to the included file only
+	let included_origin = match origin {
+	    Some(o) => Some(self.parse_name(o.as_bytes(), None,
+					    self.case_policy.owners())?),
+	    None => self.origin.clone(),
+	};
+
+	self.includes.push(Include {
+	    bufreader: BufReader::new(f),
+	    path: path,
+	    canonical_path: canonical_path,
+	    parent_line_no: self.line_no,
+	    parent_offset: self.offset,
+	    parent_origin: std::mem::replace(&mut self.origin,
+					     included_origin),
+	    parent_name: self.name.clone(),
+	});
+	self.line_no = 0;
+	self.offset = 0;
+	self.span_file = self.current_path().map(Arc::from);
+
+	return Ok(());
+    }
+
+    // Create an error at the current position. The position is the
+    // byte offset of the token in the current line.
+    fn error(&self, kind: ErrorKind, message: &str, token: &str,
+	     pos: Option<usize>) -> ParseError {
+	let mut e = ParseError::new(kind, message, token);
+	e.file = self.span_file.clone();
+	e.line = self.line_no;
+	e.column = pos.map_or(0, |p| p + 1);
+
+	// The chain of included files leading to the current position
+	for (i, include) in self.includes.iter().enumerate().rev() {
+	    let parent = match i {
+		0 => self.path.clone(),
+		_ => Some(self.includes[i - 1].path.clone()),
+	    };
+	    e.included_from.push((parent, include.parent_line_no));
+	}
+
+	return e;
+    }
+
+    // Create an I/O error at the current position, with the error as
+    // its source
+    fn io_error(&self, message: &str, token: &str, e: std::io::Error)
+		-> ParseError {
+	let mut err = self.error(ErrorKind::Io, &format!("{}: {}", message, e),
+				 token, None);
+	err.source = Some(Arc::new(e));
+	return err;
+    }
+
+    // The current origin, from the constructor, $ORIGIN or $INCLUDE
+    pub fn origin(&self) -> Option<&DomainName> {
+	return self.origin.as_ref();
+    }
+
+    // Make a relative name absolute by appending the origin. Fails for
+    // relative names when there is no origin, and when the result is
+    // too long.
+    pub fn absolute_name(&self, name: &DomainName)
+			 -> Result<DomainName, ParseError> {
+	return self.make_absolute(name, &name.to_string(), None);
+    }
+
+    fn make_absolute(&self, name: &DomainName, token: &str,
+		     pos: Option<usize>) -> Result<DomainName, ParseError> {
+	if name.is_absolute() {
+	    return Ok(name.clone());
+	}
+
+	let origin = self.origin.as_ref().ok_or_else(|| self.error(
+	    ErrorKind::MissingOrigin,
+	    &format!("No origin for relative name {}", token), token, pos))?;
+
+	return name.try_append(origin).map_err(
+	    |e| self.error(ErrorKind::BadName, &e, token, pos));
+    }
+
+    // Parse a name token. "@" is the origin. Relative names are made
+    // absolute, IDNs are optionally converted to A-labels, and the name
+    // is optionally lowercased.
+    fn parse_name(&self, word: &[u8], pos: Option<usize>, lowercase: bool)
+		  -> Result<DomainName, ParseError> {
+	let token = word.to_str_lossy();
+
+	let name = match word {
+	    b"@" => self.origin.clone().ok_or_else(|| self.error(
+		ErrorKind::MissingOrigin,
+		&format!("No origin for relative name {}", token), &token,
+		pos))?,
+	    _ => {
+		let name = DomainName::from_bytes(word).map_err(
+		    |e| self.error(ErrorKind::BadName, &e, &token, pos))?;
+		self.make_absolute(&name, &token, pos)?
+	    },
+	};
+
+	let name = match self.idna {
+	    true => name.to_ascii().map_err(
+		|e| self.error(ErrorKind::BadName, &e, &token, pos))?,
+	    false => name,
+	};
+
+	if lowercase {
+	    return Ok(name.to_lowercase());
+	}
+
+	return Ok(name);
     }
 }
 
 #[cfg(test)]
 mod tests {
     use std::fs::File;
-    use crate::zoneparser::{ZoneParser, Record, RecordData, RRClass, RRType};
+    use std::io::{BufReader, BufRead, Read};
+    use std::path::Path;
+    use std::collections::HashSet;
+    use crate::zoneparser::{ZoneParser, Record, RecordData, Span, RRClass,
+			    RRType};
+    use crate::ttl::{Ttl, TtlPolicy};
+    use crate::error::{ErrorKind, ParseError};
+    use crate::name::{DomainName, CasePolicy};
+    use crate::canonical::sort_canonical;
+    use crate::rdata::{RData, RDataError, Mx, Soa, Srv, Hinfo, Dnskey, Ds,
+		       Rrsig, Nsec3Param, Loc, Caa, Tlsa, Sshfp, Cert, Svcb,
+		       SvcParam, AplItem, IpseckeyGateway, Tsig};
 
     impl Record {
 	pub fn new_with_data(name: &str, ttl: u32, class: RRClass ,
@@ -691,15 +1575,29 @@ mod tests {
 		data.iter().map(|s| RecordData::new(s)).collect::<Vec<_>>();
 
 	    Self {
-		name: name.to_string(),
+		name: name.parse().unwrap(),
 		ttl: ttl,
 		class: class,
 		rrtype: rrtype,
+		generic: data.first() == Some(&"#"),
 		data: recorddata,
+		ttl_explicit: true,
+		span: None,
 	    }
 	}
     }
 
+    // Assert that the data of the first record of a zone is invalid,
+    // with the error message
+    macro_rules! assert_rdata_err {
+	($zone:expr, $msg:expr) => {
+	    let zone: &str = $zone;
+	    let mut p = ZoneParser::from_str(zone, "example.");
+	    let rec = p.next().unwrap().unwrap();
+	    assert_eq!(rec.rdata().unwrap_err().to_string(), $msg);
+	}
+    }
+
     macro_rules! assert_next_rec {
 	($parser:expr, $name:expr, $ttl:expr, $class:expr, $rrtype:expr, $( $data:expr ),*) => {
 	    assert_eq!(
@@ -708,11 +1606,22 @@ mod tests {
 	    );
 	}
     }
+
+    macro_rules! assert_next_err {
+	($parser:expr, $kind:expr, $msg:expr) => {
+	    match $parser.next() {
+		Some(Err(e)) => {
+		    assert_eq!(e.kind, $kind);
+		    assert_eq!(e.to_string(), $msg);
+		},
+		other => panic!("Expected error, got {:?}", other),
+	    }
+	}
+    }
     
     #[test]
     fn simple_zone() {
-	let file = File::open("./test_data/simple.zn").unwrap();
-	let mut p = ZoneParser::new(&file, "");
+	let mut p = ZoneParser::from_path("./test_data/simple.zn", "").unwrap();
 
 	assert_next_rec!(
 	    p, "simple.zn.", 3600, RRClass::IN, RRType::SOA,
@@ -739,20 +1648,20 @@ mod tests {
 
     #[test]
     fn directives() {
-	let file = File::open("./test_data/directives.zn").unwrap();
-	let mut p = ZoneParser::new(&file, "");
+	let mut p =
+	    ZoneParser::from_path("./test_data/directives.zn", "").unwrap();
 
 	assert!(p.next().is_some());
 
-	assert_eq!(p.origin, "simple.zn.");
+	assert_eq!(p.origin().unwrap(), "simple.zn.");
 
-	assert_eq!(p.default_ttl, 3600);
+	assert_eq!(p.default_ttl, Some(3600));
     }
 
     #[test]
     fn case_insensitivity() {
-	let file = File::open("./test_data/lc_and_uc.zn").unwrap();
-	let mut p = ZoneParser::new(&file, "");
+	let mut p =
+	    ZoneParser::from_path("./test_data/lc_and_uc.zn", "").unwrap();
 
 	assert_next_rec!(
 	    p, "simple.zn.", 3600, RRClass::IN, RRType::SOA,
@@ -764,8 +1673,8 @@ mod tests {
 
     #[test]
     fn relative_names() {
-	let file = File::open("./test_data/relative.zn").unwrap();
-	let mut p = ZoneParser::new(&file, "");
+	let mut p =
+	    ZoneParser::from_path("./test_data/relative.zn", "").unwrap();
         let mut optrr;
         let mut rr;
 
@@ -773,33 +1682,33 @@ mod tests {
 	assert!(optrr.is_some());
         rr = optrr.unwrap();
         assert!(rr.is_ok());
-	assert_eq!(p.absolute_name(&rr.unwrap().name), "simple.zn.");
+	assert_eq!(p.absolute_name(&rr.unwrap().name).unwrap(), "simple.zn.");
 
 	optrr = p.next();
 	assert!(optrr.is_some());
         rr = optrr.unwrap();
         assert!(rr.is_ok());
-	assert_eq!(p.absolute_name(&rr.unwrap().name), "simple.zn.");
+	assert_eq!(p.absolute_name(&rr.unwrap().name).unwrap(), "simple.zn.");
 
 	optrr = p.next();
 	assert!(optrr.is_some());
         rr = optrr.unwrap();
         assert!(rr.is_ok());
-	assert_eq!(p.absolute_name(&rr.unwrap().name), "info.simple.zn.");
+	assert_eq!(p.absolute_name(&rr.unwrap().name).unwrap(), "info.simple.zn.");
 
 	optrr = p.next();
 	assert!(optrr.is_some());
         rr = optrr.unwrap();
         assert!(rr.is_ok());
-	assert_eq!(p.absolute_name(&rr.unwrap().name), "mail.simple.zn.");
+	assert_eq!(p.absolute_name(&rr.unwrap().name).unwrap(), "mail.simple.zn.");
 
     	assert!(p.next().is_none());
     }
 
     #[test]
     fn default_values() {
-	let file = File::open("./test_data/directives.zn").unwrap();
-	let mut p = ZoneParser::new(&file, "");
+	let mut p =
+	    ZoneParser::from_path("./test_data/directives.zn", "").unwrap();
 
 	assert!(p.next().is_some());
 
@@ -814,8 +1723,8 @@ mod tests {
 
     #[test]
     fn brackets_and_comments() {
-	let file = File::open("./test_data/brackets_and_comments.zn").unwrap();
-	let mut p = ZoneParser::new(&file, "");
+	let mut p =
+	    ZoneParser::from_path("./test_data/brackets_and_comments.zn", "").unwrap();
 
 	assert_next_rec!(
 	    p, "simple.zn.", 3600, RRClass::IN, RRType::SOA,
@@ -827,8 +1736,7 @@ mod tests {
 
     #[test]
     fn quotes() {
-	let file = File::open("./test_data/quotes.zn").unwrap();
-	let mut p = ZoneParser::new(&file, "");
+	let mut p = ZoneParser::from_path("./test_data/quotes.zn", "").unwrap();
 
         assert_next_rec!(
 	    p, "simple.zn.", 3600, RRClass::IN, RRType::TXT,
@@ -839,8 +1747,8 @@ mod tests {
 
     #[test]
     fn anonymous_type() {
-	let file = File::open("./test_data/anonymous_type.zn").unwrap();
-	let mut p = ZoneParser::new(&file, "");
+	let mut p =
+	    ZoneParser::from_path("./test_data/anonymous_type.zn", "").unwrap();
 
         assert_next_rec!(
 	    p, "simple.zn.", 3600, RRClass::IN, RRType::Unknown(65535),
@@ -855,8 +1763,8 @@ mod tests {
 
     #[test]
     fn escaped_data() {
-	let file = File::open("./test_data/escaped_data.zn").unwrap();
-	let mut p = ZoneParser::new(&file, "");
+	let mut p =
+	    ZoneParser::from_path("./test_data/escaped_data.zn", "").unwrap();
 
         assert_next_rec!(
 	    p, "simple.zn.", 3600, RRClass::IN, RRType::TXT,
@@ -875,9 +1783,1738 @@ mod tests {
 
     #[test]
     fn escape_error() {
-	let file = File::open("./test_data/escape_error.zn").unwrap();
-	let mut p = ZoneParser::new(&file, "");
+	let mut p =
+	    ZoneParser::from_path("./test_data/escape_error.zn", "").unwrap();
+
+	assert_next_err!(
+	    p, ErrorKind::BadQuoting,
+	    "Bad quoting on line 1 column 24 in ./test_data/escape_error.zn");
+    }
+
+    #[test]
+    fn reader_sources() {
+	let file = File::open("./test_data/relative.zn").unwrap();
+	let mut p = ZoneParser::new(BufReader::new(&file), "");
+
+	assert!(p.next().is_some());
+	assert_next_rec!(
+	    p, "simple.zn.", 3600, RRClass::IN, RRType::NS, "ns1.simple.zn.");
+
+	let mut p = ZoneParser::from_str(
+	    "mail\t3600\tIN\tA\t1.2.3.4\n", "simple.zn");
+
+	assert_next_rec!(
+	    p, "mail.simple.zn.", 3600, RRClass::IN, RRType::A, "1.2.3.4");
+	assert!(p.next().is_none());
+
+	let mut p = ZoneParser::from_bytes(
+	    b"simple.zn. 3600 IN NS ns1.simple.zn.", "");
 
-	assert_eq!(p.next(), Some(Err("Bad quoting on line 1".to_string())));
+	assert_next_rec!(
+	    p, "simple.zn.", 3600, RRClass::IN, RRType::NS, "ns1.simple.zn.");
+	assert!(p.next().is_none());
+    }
+
+    #[test]
+    fn parser_is_send() {
+	fn assert_send<T: Send>(_: &T) {}
+
+	let p = ZoneParser::from_path("./test_data/simple.zn", "").unwrap();
+	assert_send(&p);
+
+	let handle = std::thread::spawn(move || p.count());
+	assert_eq!(handle.join().unwrap(), 6);
+    }
+
+    #[test]
+    fn include() {
+	let mut p = ZoneParser::from_path("./test_data/include.zn", "").unwrap();
+
+	assert!(p.next().is_some());
+
+	assert_next_rec!(
+	    p, "sub.simple.zn.", 3600, RRClass::IN, RRType::A, "1.2.3.4");
+
+	assert_next_rec!(
+	    p, "www.sub.simple.zn.", 3600, RRClass::IN, RRType::A, "1.2.3.5");
+
+	// Origin and name are restored after the included file
+	assert_next_rec!(
+	    p, "simple.zn.", 3600, RRClass::IN, RRType::NS, "ns1.simple.zn.");
+
+	assert_next_rec!(
+	    p, "simple.zn.", 3600, RRClass::IN, RRType::A, "1.2.3.4");
+
+	assert_next_rec!(
+	    p, "www.simple.zn.", 3600, RRClass::IN, RRType::A, "1.2.3.5");
+
+	assert!(p.next().is_none());
+    }
+
+    #[test]
+    fn include_dir() {
+	let zone = "$ORIGIN simple.zn.\n$INCLUDE include_sub.zn\n";
+	let mut p = ZoneParser::from_str(zone, "");
+	p.set_include_dir("./test_data");
+
+	assert_next_rec!(
+	    p, "simple.zn.", 0, RRClass::IN, RRType::A, "1.2.3.4");
+    }
+
+    #[test]
+    fn include_errors() {
+	let mut p = ZoneParser::from_path("./test_data/include.zn", "").unwrap();
+	p.set_max_include_depth(0);
+
+	assert!(p.next().is_some());
+	assert_next_err!(
+	    p, ErrorKind::BadInclude,
+	    "Include depth exceeded including include_sub.zn on line 4 \
+	     in ./test_data/include.zn");
+
+	let mut p =
+	    ZoneParser::from_path("./test_data/include_cycle.zn", "").unwrap();
+
+	assert!(p.next().is_some());
+	assert_next_err!(
+	    p, ErrorKind::BadInclude,
+	    "Include cycle including ./test_data/include_cycle.zn on line 1 \
+	     in ./test_data/include_cycle_sub.zn, included from \
+	     ./test_data/include_cycle.zn line 2");
+    }
+
+    #[test]
+    fn generate() {
+	let mut p =
+	    ZoneParser::from_path("./test_data/generate.zn", "").unwrap();
+
+	assert_next_rec!(
+	    p, "1.2.0.192.in-addr.arpa.", 3600, RRClass::IN, RRType::PTR,
+	    "host-1.example.");
+
+	assert_next_rec!(
+	    p, "2.2.0.192.in-addr.arpa.", 3600, RRClass::IN, RRType::PTR,
+	    "host-2.example.");
+
+	for i in [0, 4, 8] {
+	    let name = format!("{:03}.2.0.192.in-addr.arpa.", i + 10);
+	    let addr = format!("10.0.0.{}", i);
+	    assert_next_rec!(p, &name, 300, RRClass::IN, RRType::A, &addr);
+	}
+
+	assert_next_rec!(
+	    p, "a.1.ip6.", 3600, RRClass::IN, RRType::PTR, "h1a-A.1.example.");
+
+	assert_next_rec!(
+	    p, "b.1.ip6.", 3600, RRClass::IN, RRType::PTR, "h1b-B.1.example.");
+
+	assert_next_rec!(
+	    p, "cost.2.0.192.in-addr.arpa.", 3600, RRClass::IN, RRType::TXT,
+	    "costs $5 $");
+
+	assert!(p.next().is_none());
+    }
+
+    #[test]
+    fn generate_errors() {
+	let mut p = ZoneParser::from_str("$GENERATE 5-1 $ A 1.2.3.$\n", "");
+	assert_next_err!(
+	    p, ErrorKind::BadDirective,
+	    "Bad $GENERATE range 5-1 on line 1 column 11");
+
+	let mut p = ZoneParser::from_str("$GENERATE 1-2 ${1,2,q} A 1.2.3.$\n", "");
+	assert_next_err!(
+	    p, ErrorKind::BadDirective,
+	    "Bad $GENERATE modifier in ${1,2,q} on line 1");
+	assert!(p.next().is_none());
+    }
+
+    #[test]
+    fn ttl_units() {
+	let mut p =
+	    ZoneParser::from_path("./test_data/ttl_units.zn", "").unwrap();
+
+	assert_next_rec!(
+	    p, "simple.zn.", 172800, RRClass::IN, RRType::SOA,
+	    "ns1.simple.zn.", "hostmaster.simple.zn.",
+	    "2024090906", "7200", "1800", "777600", "5400");
+
+	assert_next_rec!(
+	    p, "simple.zn.", 3600, RRClass::IN, RRType::NS, "ns1.simple.zn.");
+
+	assert_next_rec!(
+	    p, "simple.zn.", 694861, RRClass::IN, RRType::NS, "ns2.simple.zn.");
+
+	assert!(p.next().is_none());
+
+	assert_eq!("1H30m".parse(), Ok(Ttl(5400)));
+	assert_eq!("300".parse(), Ok(Ttl(300)));
+	assert!("1h30".parse::<Ttl>().is_err());
+	assert!("h".parse::<Ttl>().is_err());
+	assert!("1y".parse::<Ttl>().is_err());
+	assert!("9999999w".parse::<Ttl>().is_err());
+
+	assert_eq!(Ttl(694861).to_string(), "1w1d1h1m1s");
+	assert_eq!(Ttl(5400).to_string(), "1h30m");
+	assert_eq!(Ttl(0).to_string(), "0s");
+
+	let mut p = ZoneParser::from_str("simple.zn. 1x IN A 1.2.3.4\n", "");
+	assert_next_err!(
+	    p, ErrorKind::BadTtl, "Bad TTL 1x on line 1 column 12");
+
+	// Generic SOA data has no timers to convert
+	let mut p = ZoneParser::from_str(
+	    "@ 1 SOA \\# 22 00 00 00000001 00000002 0000000A 0B 0C 0D 0E \
+	     00000005\n\
+	     @ 1 TYPE6 \\# 4 1h 00 00 00\n", "simple.zn.");
+	let rec = p.next().unwrap().unwrap();
+	assert_eq!(rec.data[6].as_bytes(), b"0000000A");
+	assert_eq!(rec.rdata(), Ok(RData::Generic(vec!(
+	    0, 0, 0, 0, 0, 1, 0, 0, 0, 2, 0, 0, 0, 10, 11, 12, 13, 14,
+	    0, 0, 0, 5))));
+	assert_next_rec!(
+	    p, "simple.zn.", 1, RRClass::IN, RRType::SOA,
+	    "#", "4", "1h", "00", "00", "00");
+    }
+
+    #[test]
+    fn error_kinds() {
+	let mut p = ZoneParser::from_str("simple.zn. 300 IN FOO bar\n", "");
+	assert_next_err!(
+	    p, ErrorKind::UnknownType, "Unknown type foo on line 1 column 19");
+
+	let mut p = ZoneParser::from_str("simple.zn. 300 CLASS9 A 1.2.3.4\n", "");
+	assert_next_err!(
+	    p, ErrorKind::UnknownClass,
+	    "Unknown class class9 on line 1 column 16");
+
+	let mut p = ZoneParser::from_str("$FOO bar\n", "");
+	assert_next_err!(
+	    p, ErrorKind::UnknownDirective,
+	    "Unknown directive $foo on line 1 column 1");
+
+	let mut p = ZoneParser::from_str("simple.zn. 300 IN NS (\n ns1.\n", "");
+	assert_next_err!(
+	    p, ErrorKind::UnbalancedParentheses,
+	    "Unexpected end of file in parentheses on line 2");
+
+	let mut p = ZoneParser::from_str("simple.zn. 300 IN TXT \"foo\n", "");
+	assert_next_err!(
+	    p, ErrorKind::BadQuoting,
+	    "Unterminated quoted string on line 1");
+
+	let mut p = ZoneParser::from_str("simple.zn. 300 IN TXT (\"foo\n", "");
+	assert_next_err!(
+	    p, ErrorKind::BadQuoting,
+	    "Unexpected end of file in quoted string on line 1");
+
+	let mut p = ZoneParser::from_str("simple.zn. 300 IN TXT foo\\", "");
+	match p.next() {
+	    Some(Err(e)) => {
+		assert_eq!(e.kind, ErrorKind::BadEscaping);
+		assert_eq!(e.token, "foo\\");
+		assert_eq!(e.file, None);
+		assert_eq!((e.line, e.column), (1, 23));
+	    },
+	    other => panic!("Expected error, got {:?}", other),
+	}
+    }
+
+    #[test]
+    fn regressions() {
+	// Inputs which used to make the parser panic
+	for entry in std::fs::read_dir("./test_data/regressions").unwrap() {
+	    let path = entry.unwrap().path();
+	    for recover in [false, true] {
+		let mut p = ZoneParser::from_path(&path, "").unwrap();
+		p.set_max_include_depth(0);
+		p.set_error_recovery(recover);
+
+		for _ in p.take(10000) { }
+	    }
+	}
+
+	let mut p = ZoneParser::from_str("simple.zn. 300 IN A 1.2.3.4)\n", "");
+	assert_next_err!(
+	    p, ErrorKind::UnbalancedParentheses,
+	    "Unbalanced closing parenthesis on line 1 column 28");
+
+	let mut p = ZoneParser::from_str("simple.zn. 300 IN TYPEabc 1\n", "");
+	assert_next_err!(
+	    p, ErrorKind::UnknownType,
+	    "Unknown type typeabc on line 1 column 19");
+
+	let zone = "(".repeat(70000);
+	let mut p = ZoneParser::from_str(&zone, "");
+	assert_next_err!(
+	    p, ErrorKind::UnbalancedParentheses,
+	    "Too many open parentheses on line 1 column 65536");
+
+	let p = ZoneParser::from_str("", "");
+	assert!(p.rrclass_from_str("XX").is_err());
+	assert!(p.rrtype_from_str("TYPE65536").is_err());
+	assert!(p.rrtype_bm_from_str("TYPEx").is_err());
+	assert_eq!(p.rrtype_from_str("TYPE65535"), Ok(RRType::Unknown(65535)));
+    }
+
+    #[test]
+    fn binary_data() {
+	let mut p = ZoneParser::from_path("./test_data/binary.zn", "no.").unwrap();
+
+	let rr = p.next().unwrap().unwrap();
+	assert_eq!(rr.data[0].as_bytes(), b"\xff\xfe\x01");
+	assert_eq!(rr.data[1].as_bytes(), b"A\xff\x09");
+	assert_eq!(rr.data[2].as_bytes(), b"bin\x00ary");
+	assert_eq!(rr.data[2].as_str(), Some("bin\0ary"));
+	assert_eq!(rr.data[0].as_str(), None);
+	assert_eq!(rr.to_string(),
+		   "simple.zn. 3600 IN TXT \\255\\254\\001 \
+		    A\\255\\009 bin\\000ary");
+
+	// Non-ASCII owner names are escaped
+	let rr = p.next().unwrap().unwrap();
+	assert_eq!(rr.name, "\\195\\166\\195\\184\\195\\165.no.");
+	assert_eq!(rr.data[0].as_bytes(), b"bl\xe5b\xc3\xa6r");
+
+	assert!(p.next().is_none());
+
+	// Names keep their escapes, other fields are unescaped
+	let zone = b"a 300 IN MX 1\\050 m\\.x\\255\xff\n";
+	let rr = ZoneParser::from_bytes(zone, "no.").next().unwrap().unwrap();
+	assert!(!rr.name_field(0) && rr.name_field(1));
+	assert_eq!(rr.data[0].as_bytes(), b"12");
+	assert_eq!(rr.data[1].as_bytes(), b"m\\.x\\255\\255.no.");
+	assert_eq!(rr.to_string(), "a.no. 300 IN MX 12 m\\.x\\255\\255.no.");
+
+	let mut p = ZoneParser::from_bytes(zone, "no.");
+	p.set_absolute_rdata_names(false);
+	let rr = p.next().unwrap().unwrap();
+	assert_eq!(rr.data[1].as_bytes(), b"m\\.x\\255\xff");
+	assert_eq!(rr.to_string(), "a.no. 300 IN MX 12 m\\.x\\255\\255");
+
+	let mut p = ZoneParser::from_str("simple.zn. 300 IN TXT \\25x\n", "");
+	assert_next_err!(
+	    p, ErrorKind::BadEscaping, "Bad escaping on line 1 column 23");
+
+	let mut p = ZoneParser::from_str("simple.zn. 300 IN TXT \\256\n", "");
+	assert_next_err!(
+	    p, ErrorKind::BadEscaping, "Bad escaping on line 1 column 23");
+
+	assert_eq!(RecordData::new("a \"b\"").to_string(), "\"a \\\"b\\\"\"");
+	assert_eq!(RecordData::new("").to_string(), "\"\"");
+    }
+
+    #[test]
+    fn error_recovery() {
+	let mut p = ZoneParser::from_path("./test_data/recovery.zn", "rec.zn")
+	    .unwrap();
+	p.set_error_recovery(true);
+
+	assert_next_rec!(
+	    p, "a.rec.zn.", 3600, RRClass::IN, RRType::A, "10.0.0.1");
+	assert_next_err!(
+	    p, ErrorKind::UnknownType,
+	    "Unknown type bogus on line 3 column 6 in ./test_data/recovery.zn");
+	assert_next_err!(
+	    p, ErrorKind::BadQuoting,
+	    "Bad quoting on line 5 column 3 in ./test_data/recovery.zn");
+	assert_next_rec!(
+	    p, "d.rec.zn.", 3600, RRClass::IN, RRType::A, "10.0.0.4");
+	assert_next_err!(
+	    p, ErrorKind::BadQuoting,
+	    "Bad quoting on line 8 column 14 in ./test_data/recovery.zn");
+	assert_next_rec!(
+	    p, "f.rec.zn.", 3600, RRClass::IN, RRType::A, "10.0.0.6");
+	assert_next_err!(
+	    p, ErrorKind::UnknownDirective,
+	    "Unknown directive $foo on line 11 column 1 in \
+	     ./test_data/recovery.zn");
+	assert_next_err!(
+	    p, ErrorKind::BadQuoting,
+	    "Unterminated quoted string on line 12 in ./test_data/recovery.zn");
+	assert_next_err!(
+	    p, ErrorKind::BadTtl,
+	    "Bad TTL 1x on line 13 column 3 in ./test_data/recovery.zn");
+	assert_next_err!(
+	    p, ErrorKind::UnbalancedParentheses,
+	    "Unbalanced closing parenthesis on line 14 column 17 in \
+	     ./test_data/recovery.zn");
+	assert_next_rec!(
+	    p, "j.rec.zn.", 3600, RRClass::IN, RRType::A, "10.0.0.10");
+	assert_next_err!(
+	    p, ErrorKind::BadQuoting,
+	    "Unexpected end of file in quoted string on line 16 in \
+	     ./test_data/recovery.zn");
+	assert!(p.next().is_none());
+
+	// Errors in generated lines don't end the $GENERATE
+	let mut p = ZoneParser::from_str(
+	    "$GENERATE 1-3 h$ BOGUS 10.0.0.$\n\
+	     $GENERATE 8-12 ${0,62,d}${0,0,d} A 10.0.0.$\n\
+	     x A ( 10.0.0.1\n", "rec.zn");
+	p.set_error_recovery(true);
+	for _ in 1..=3 {
+	    assert_next_err!(
+		p, ErrorKind::UnknownType,
+		"Unknown type bogus on line 1 column 4");
+	}
+	let generated: Vec<_> = p.by_ref().take(5)
+	    .map(|r| r.map(|r| r.data[0].to_string()).map_err(|e| e.kind))
+	    .collect();
+	assert_eq!(generated, vec!(
+	    Ok("10.0.0.8".to_string()), Ok("10.0.0.9".to_string()),
+	    Err(ErrorKind::BadName), Err(ErrorKind::BadName),
+	    Err(ErrorKind::BadName)));
+	assert_next_err!(
+	    p, ErrorKind::UnbalancedParentheses,
+	    "Unexpected end of file in parentheses on line 3");
+	assert!(p.next().is_none());
+
+	// A read error ends the file, or the included file
+	struct Broken;
+	impl Read for Broken {
+	    fn read(&mut self, _: &mut [u8]) -> std::io::Result<usize> {
+		return Err(std::io::Error::other("broken"));
+	    }
+	}
+	let reader = BufReader::new(b"a 300 IN A 10.0.0.1\n".chain(Broken));
+	let mut p = ZoneParser::new(reader, "rec.zn");
+	p.set_error_recovery(true);
+	assert_next_rec!(
+	    p, "a.rec.zn.", 300, RRClass::IN, RRType::A, "10.0.0.1");
+	let err = p.next().unwrap().unwrap_err();
+	assert_eq!(err.to_string(), "Error reading zonefile: broken on line 1");
+	let source = std::error::Error::source(&err).unwrap();
+	assert_eq!(source.to_string(), "broken");
+	assert!(p.next().is_none());
+
+	let mut p = ZoneParser::from_str(
+	    "$INCLUDE ./test_data\nb 300 IN A 10.0.0.2\n", "rec.zn");
+	p.set_error_recovery(true);
+	let err = p.next().unwrap().unwrap_err();
+	assert_eq!(err.kind, ErrorKind::Io);
+	assert_eq!(err.source.unwrap().kind(),
+		   std::io::ErrorKind::IsADirectory);
+	assert_next_rec!(
+	    p, "b.rec.zn.", 300, RRClass::IN, RRType::A, "10.0.0.2");
+	assert!(p.next().is_none());
+    }
+
+    #[test]
+    fn spans() {
+	let mut p = ZoneParser::from_path("./test_data/include.zn", "").unwrap();
+	p.set_spans(true);
+	let file = Some(Path::new("./test_data/include.zn").into());
+	let sub = Some(Path::new("./test_data/include_sub.zn").into());
+
+	fn span<R: BufRead>(p: &mut ZoneParser<R>) -> Option<Span> {
+	    return p.next().unwrap().unwrap().span.map(|s| *s);
+	}
+
+	assert_eq!(span(&mut p), Some(Span {
+	    file: file.clone(), start_line: 3, end_line: 3,
+	    start_offset: 29, end_offset: 107 }));
+	assert_eq!(span(&mut p), Some(Span {
+	    file: sub.clone(), start_line: 1, end_line: 1,
+	    start_offset: 0, end_offset: 15 }));
+	assert_eq!(span(&mut p), Some(Span {
+	    file: sub.clone(), start_line: 2, end_line: 2,
+	    start_offset: 15, end_offset: 32 }));
+	assert_eq!(span(&mut p), Some(Span {
+	    file: file.clone(), start_line: 5, end_line: 5,
+	    start_offset: 146, end_offset: 168 }));
+
+	// Multi-line records and generated records
+	let mut p = ZoneParser::from_str(
+	    "a IN TXT ( x\n y )\n$GENERATE 1-2 h$ A 10.0.0.$\n\
+	     b IN A 1.2.3.4", "span.zn");
+	p.set_spans(true);
+	assert_eq!(span(&mut p), Some(Span {
+	    file: None, start_line: 1, end_line: 2,
+	    start_offset: 0, end_offset: 18 }));
+	for _ in 0..2 {
+	    assert_eq!(span(&mut p), Some(Span {
+		file: None, start_line: 3, end_line: 3,
+		start_offset: 18, end_offset: 46 }));
+	}
+	assert_eq!(span(&mut p), Some(Span {
+	    file: None, start_line: 4, end_line: 4,
+	    start_offset: 46, end_offset: 60 }));
+
+	// Spans are off by default
+	let mut p = ZoneParser::from_str("a IN A 1.2.3.4\n", "span.zn");
+	assert_eq!(span(&mut p), None);
+    }
+
+    #[test]
+    fn ttl_policy() {
+	fn ttls<R: BufRead>(p: &mut ZoneParser<R>) -> Vec<(u32, bool)> {
+	    return p.by_ref().map(|r| r.map(|r| (r.ttl, r.ttl_explicit))
+				  .unwrap_or((u32::MAX, false)))
+		.collect();
+	}
+
+	// The TTL of the last record is used until $TTL. $TTL 0 is valid.
+	let mut p = ZoneParser::from_path("./test_data/ttl_policy.zn", "t.zn")
+	    .unwrap();
+	assert_eq!(ttls(&mut p), vec!(
+	    (0, false), (0, false), (600, true), (600, false),
+	    (0, false), (60, true), (0, false)));
+	let warnings = p.take_warnings();
+	assert_eq!(warnings.len(), 1);
+	assert_eq!(warnings[0].kind, ErrorKind::MissingTtl);
+	assert_eq!(warnings[0].to_string(),
+		   "No $TTL, using the last TTL on line 1 in \
+		    ./test_data/ttl_policy.zn");
+	assert!(p.take_warnings().is_empty());
+
+	let mut p = ZoneParser::from_path("./test_data/ttl_policy.zn", "t.zn")
+	    .unwrap();
+	p.set_ttl_policy(TtlPolicy::LegacySoa);
+	assert_eq!(ttls(&mut p), vec!(
+	    (300, false), (300, false), (600, true), (300, false),
+	    (0, false), (60, true), (0, false)));
+	assert_eq!(p.take_warnings()[0].message,
+		   "No $TTL, using the SOA minimum");
+
+	// Records get the TTL of $TTL, and without it, a missing TTL is an
+	// error
+	let mut p = ZoneParser::from_path("./test_data/ttl_policy.zn", "t.zn")
+	    .unwrap();
+	p.set_ttl_policy(TtlPolicy::Strict);
+	p.set_error_recovery(true);
+	assert_next_err!(
+	    p, ErrorKind::MissingTtl,
+	    "Missing TTL for t.zn. on line 1 in ./test_data/ttl_policy.zn");
+	assert_next_err!(
+	    p, ErrorKind::MissingTtl,
+	    "Missing TTL for t.zn. on line 2 in ./test_data/ttl_policy.zn");
+	assert_eq!(p.next().unwrap().unwrap().ttl, 600);
+	assert_next_err!(
+	    p, ErrorKind::MissingTtl,
+	    "Missing TTL for a.t.zn. on line 4 in ./test_data/ttl_policy.zn");
+	assert_eq!(ttls(&mut p), vec!((0, false), (60, true), (0, false)));
+	assert!(p.take_warnings().is_empty());
+    }
+
+    #[test]
+    fn domain_names() {
+	let name: DomainName = "WWW.Ex\\.ample.com.".parse().unwrap();
+	let labels: Vec<&[u8]> = name.labels().collect();
+	assert_eq!(labels, vec!(&b"WWW"[..], b"Ex.ample", b"com"));
+	assert_eq!(name.label_count(), 3);
+	assert!(name.is_absolute());
+	assert_eq!(name.to_string(), "WWW.Ex\\.ample.com.");
+	assert_eq!(name.to_lowercase().to_string(), "www.ex\\.ample.com.");
+
+	// Case-insensitive comparison and hashing
+	assert_eq!(name, "www.ex\\.AMPLE.com.");
+	assert_ne!(name, "www.ex.ample.com.");
+	assert_ne!(name, "www.ex\\.ample.com");
+	let mut set = std::collections::HashSet::new();
+	set.insert(name.clone());
+	assert!(set.contains(&"www.EX\\.ample.COM.".parse().unwrap()));
+
+	// Decimal escapes
+	let escaped: DomainName = "a\\046b\\255.".parse().unwrap();
+	assert_eq!(escaped.labels().next(), Some(&b"a.b\xff"[..]));
+	assert_eq!(escaped.to_string(), "a\\.b\\255.");
+
+	let parent = name.parent().unwrap();
+	assert_eq!(parent, "ex\\.ample.com.");
+	assert!(name.is_child_of(&parent));
+	assert!(parent.is_parent_of(&name));
+	assert!(!name.is_child_of(&parent.parent().unwrap()));
+	assert!(name.is_subdomain_of(&"COM.".parse().unwrap()));
+	assert!(name.is_subdomain_of(&name));
+	assert!(name.is_subdomain_of(&DomainName::root()));
+	assert!(!name.is_subdomain_of(&"ample.com.".parse().unwrap()));
+	assert_eq!(parent.child(b"www").unwrap(), name);
+	assert!(DomainName::root().parent().is_none());
+	assert_eq!(DomainName::root().to_string(), ".");
+	assert_eq!(DomainName::root().label_count(), 0);
+
+	let relative: DomainName = "a.b".parse().unwrap();
+	assert!(!relative.is_absolute());
+	assert_eq!(relative.append(&parent).to_string(),
+		   "a.b.Ex\\.ample.com.");
+
+	assert_eq!("a\\25".parse::<DomainName>().unwrap_err(),
+		   "Bad escaping in name a\\25");
+	assert!(format!("{}.com", "a".repeat(64)).parse::<DomainName>()
+		.is_err());
+	assert!(DomainName::root().child(&b"a".repeat(63)).is_ok());
+
+	// Names in zonefiles
+	let mut p = ZoneParser::from_str(
+	    "A\\.b 300 IN A 10.0.0.1\n\
+	     a\\25 300 IN A 10.0.0.2\n", "Example.COM");
+	let rr = p.next().unwrap().unwrap();
+	assert_eq!(rr.name.to_string(), "a\\.b.example.com.");
+	assert_eq!(rr.name.label_count(), 3);
+	assert_next_err!(
+	    p, ErrorKind::BadName, "Bad escaping in name a\\25 on line 2 column 1");
+
+	let mut p = ZoneParser::from_str("", "a\\25");
+	assert_next_err!(
+	    p, ErrorKind::BadName, "Bad escaping in name a\\25");
+	assert!(p.next().is_none());
+    }
+
+    #[test]
+    fn canonical_order() {
+	// Example from RFC 4034 section 6.1
+	let ordered = [
+	    "example.", "a.example.", "yljkjljk.a.example.", "Z.a.example.",
+	    "zABC.a.EXAMPLE.", "z.example.", "\\001.z.example.",
+	    "*.z.example.", "\\200.z.example.",
+	];
+	let mut names: Vec<DomainName> =
+	    ordered.iter().rev().map(|n| n.parse().unwrap()).collect();
+	names.sort();
+	assert_eq!(names, ordered);
+	assert!("a.example".parse::<DomainName>().unwrap() <
+		"a.example.".parse::<DomainName>().unwrap());
+
+	let mut p = ZoneParser::from_str(
+	    "b 300 IN A 10.0.0.1\n\
+	     a 300 IN TYPE300 \\# 0\n\
+	     a 300 IN DLV 1 2 3 abcd\n\
+	     a 300 IN A 10.0.0.2\n\
+	     a 300 IN A 9.0.0.1\n\
+	     a 300 IN MX 10 B.example.\n\
+	     a 300 IN MX 10 a.example.\n\
+	     a 300 IN MX 9 z.example.\n\
+	     A 300 IN TXT b\n\
+	     a 300 IN TXT ab\n", "example.");
+	let mut records: Vec<Record> = p.by_ref().map(|r| r.unwrap()).collect();
+	sort_canonical(&mut records);
+	let sorted: Vec<String> = records.iter().map(|r| r.to_string())
+	    .collect();
+	assert_eq!(sorted, vec!(
+	    "a.example. 300 IN A 9.0.0.1",
+	    "a.example. 300 IN A 10.0.0.2",
+	    "a.example. 300 IN MX 9 z.example.",
+	    "a.example. 300 IN MX 10 a.example.",
+	    "a.example. 300 IN MX 10 B.example.",
+	    "a.example. 300 IN TXT b",
+	    "a.example. 300 IN TXT ab",
+	    "a.example. 300 IN Unknown(300) \\# 0",
+	    "a.example. 300 IN DLV 1 2 3 abcd",
+	    "b.example. 300 IN A 10.0.0.1",
+	));
+
+	// Generic data sorts by its wire format, like typed data, and hex
+	// digits by value in either case
+	let mut p = ZoneParser::from_str(
+	    "a 300 IN TYPE1 \\# 4 0A000002\n\
+	     a 300 IN A 10.0.0.1\n\
+	     a 300 IN A 10.0.0.3\n\
+	     a 300 IN DS 1 8 2 \
+	     0000000000000000000000000000000000000000000000000000000000000aFF\n\
+	     a 300 IN DS 1 8 2 \
+	     0000000000000000000000000000000000000000000000000000000000000B00\n\
+	     a 300 IN MX \\# 12 000a 01 42 07 6578616d706c65 00\n\
+	     a 300 IN MX 10 a.example.\n", "example.");
+	let mut records: Vec<Record> = p.by_ref().map(|r| r.unwrap()).collect();
+	sort_canonical(&mut records);
+	assert!(records.windows(2).all(
+	    |w| w[0].cmp_canonical(&w[1]) == std::cmp::Ordering::Less));
+	let sorted: Vec<String> = records.iter().map(|r| r.to_string())
+	    .collect();
+	let zeros = "0".repeat(61);
+	assert_eq!(sorted, vec!(
+	    "a.example. 300 IN A 10.0.0.1".to_string(),
+	    "a.example. 300 IN A \\# 4 0A000002".to_string(),
+	    "a.example. 300 IN A 10.0.0.3".to_string(),
+	    "a.example. 300 IN MX 10 a.example.".to_string(),
+	    "a.example. 300 IN MX \\# 12 000a 01 42 07 6578616d706c65 00"
+		.to_string(),
+	    format!("a.example. 300 IN DS 1 8 2 {}aFF", zeros),
+	    format!("a.example. 300 IN DS 1 8 2 {}B00", zeros),
+	));
+    }
+
+    #[test]
+    fn rdata_names() {
+	let key = "AQNRU3mG7TVTO2BkR47usntb102uFJtugbo6BSGvgqt4AQ==";
+	let mut p = ZoneParser::from_path("./test_data/rdata_names.zn", "")
+	    .unwrap();
+
+	let rr = p.next().unwrap().unwrap();
+	assert_eq!(rr, Record::new_with_data(
+	    "example.", 300, RRClass::IN, RRType::SOA,
+	    vec!("ns1.example.", "Host\\.Master.example.", "1", "2", "3",
+		 "4", "5")));
+	assert_eq!(rr.to_string(),
+		   "example. 300 IN SOA ns1.example. Host\\.Master.example. \
+		    1 2 3 4 5");
+	assert_next_rec!(
+	    p, "example.", 300, RRClass::IN, RRType::NS, "ns1.example.");
+	assert_next_rec!(
+	    p, "example.", 300, RRClass::IN, RRType::NS, "ns2.example.net.");
+	assert_next_rec!(
+	    p, "example.", 300, RRClass::IN, RRType::MX, "10", "example.");
+	assert_next_rec!(
+	    p, "www.example.", 300, RRClass::IN, RRType::CNAME, "example.");
+	assert_next_rec!(
+	    p, "_sip._tcp.example.", 300, RRClass::IN, RRType::SRV,
+	    "0", "5", "5060", "sip.example.");
+	assert_next_rec!(
+	    p, "mail.example.", 300, RRClass::IN, RRType::TXT, "mail");
+	assert_next_rec!(
+	    p, "gw.example.", 300, RRClass::IN, RRType::IPSECKEY,
+	    "10", "3", "2", "gw1.example.", key);
+	assert_next_rec!(
+	    p, "gw.example.", 300, RRClass::IN, RRType::IPSECKEY,
+	    "10", "1", "2", "192.0.2.38", key);
+	assert_next_rec!(
+	    p, "ptr.example.", 300, RRClass::IN, RRType::PTR,
+	    "#", "3", "016100");
+	assert!(p.next().is_none());
+
+	// Names kept as written
+	let mut p = ZoneParser::from_path("./test_data/rdata_names.zn", "")
+	    .unwrap();
+	p.set_absolute_rdata_names(false);
+	let rr = p.next().unwrap().unwrap();
+	assert_eq!(rr.data[1].as_str(), Some("Host\\.Master"));
+	assert_next_rec!(
+	    p, "example.", 300, RRClass::IN, RRType::NS, "ns1");
+	p.next();
+	assert_next_rec!(
+	    p, "example.", 300, RRClass::IN, RRType::MX, "10", "@");
+	let mut p = ZoneParser::from_bytes(b"a 300 IN NS N\xffs\\.x\n", "ex");
+	p.set_absolute_rdata_names(false);
+	p.set_case_policy(CasePolicy::Lowercase);
+	assert_eq!(p.next().unwrap().unwrap().data[0].as_bytes(),
+		   b"n\xffs\\.x");
+
+	let mut p = ZoneParser::from_str("@ 300 IN NS a\\25\n", "example");
+	assert_next_err!(
+	    p, ErrorKind::BadName,
+	    "Bad escaping in name a\\25 on line 1 column 13");
+    }
+
+    #[test]
+    fn origin() {
+	let mut p = ZoneParser::from_path("./test_data/origin.zn", "").unwrap();
+	assert_eq!(p.origin(), None);
+	assert_next_rec!(
+	    p, "a.example.", 300, RRClass::IN, RRType::A, "10.0.0.1");
+	assert_eq!(p.origin().unwrap(), "example.");
+	assert_next_rec!(
+	    p, "b.sub.example.", 300, RRClass::IN, RRType::A, "10.0.0.2");
+	assert_next_rec!(
+	    p, "c.sub.example.", 300, RRClass::IN, RRType::NS,
+	    "d.sub.example.");
+	assert_eq!(p.origin().unwrap(), "sub.example.");
+	assert_next_err!(
+	    p, ErrorKind::BadName,
+	    "Bad escaping in name bad\\25 on line 7 column 9 in \
+	     ./test_data/origin.zn");
+
+	// Relative names need an origin
+	let mut p = ZoneParser::from_str(
+	    "example. 300 IN NS ns\n\
+	     @ 300 IN A 10.0.0.1\n\
+	     www 300 IN A 10.0.0.1\n", "");
+	p.set_error_recovery(true);
+	assert_next_err!(
+	    p, ErrorKind::MissingOrigin,
+	    "No origin for relative name ns on line 1 column 20");
+	assert_next_err!(
+	    p, ErrorKind::MissingOrigin,
+	    "No origin for relative name @ on line 2 column 1");
+	assert_next_err!(
+	    p, ErrorKind::MissingOrigin,
+	    "No origin for relative name www on line 3 column 1");
+
+	let p = ZoneParser::from_str("", "example");
+	assert_eq!(p.origin().unwrap().to_string(), "example.");
+	let p = ZoneParser::from_str("", ".");
+	assert!(p.origin().unwrap().is_root());
+    }
+
+    #[test]
+    fn case_policy() {
+	let zone = "$ORIGIN Sub.Example.\n\
+		    WWW 300 IN MX 10 Mail\n\
+		    @ 300 IN SOA NS1.Example. Host.Master.Example. 1 2 3 4 5\n";
+	let records = |policy, absolute| {
+	    let mut p = ZoneParser::from_str(zone, "");
+	    p.set_case_policy(policy);
+	    p.set_absolute_rdata_names(absolute);
+	    return p.map(|r| r.unwrap().to_string()).collect::<Vec<_>>();
+	};
+
+	assert_eq!(records(CasePolicy::Preserve, true), vec!(
+	    "WWW.Sub.Example. 300 IN MX 10 Mail.Sub.Example.",
+	    "Sub.Example. 300 IN SOA NS1.Example. Host.Master.Example. \
+	     1 2 3 4 5"));
+	assert_eq!(records(CasePolicy::LowercaseOwners, true), vec!(
+	    "www.sub.example. 300 IN MX 10 Mail.sub.example.",
+	    "sub.example. 300 IN SOA NS1.Example. Host.Master.Example. \
+	     1 2 3 4 5"));
+	assert_eq!(records(CasePolicy::Lowercase, true), vec!(
+	    "www.sub.example. 300 IN MX 10 mail.sub.example.",
+	    "sub.example. 300 IN SOA ns1.example. host.master.example. \
+	     1 2 3 4 5"));
+	assert_eq!(records(CasePolicy::Lowercase, false)[0],
+		   "www.sub.example. 300 IN MX 10 mail");
+	assert_eq!(records(CasePolicy::Preserve, false)[0],
+		   "WWW.Sub.Example. 300 IN MX 10 Mail");
+    }
+
+    #[test]
+    fn idna() {
+	let zone = "$ORIGIN bücher.no.\n\
+		    www 300 IN A 10.0.0.1\n\
+		    bücher.example. 300 IN CNAME @\n\
+		    xn--bcher-kva 300 IN A 10.0.0.2\n\
+		    XN--BCHER-KVA 300 IN A 10.0.0.3\n";
+	let mut p = ZoneParser::from_str(zone, "");
+	p.set_idna(true);
+	let records: Vec<String> = p.map(|r| r.unwrap().to_string())
+	    .collect();
+	assert_eq!(records, vec!(
+	    "www.xn--bcher-kva.no. 300 IN A 10.0.0.1",
+	    "xn--bcher-kva.example. 300 IN CNAME xn--bcher-kva.no.",
+	    "xn--bcher-kva.xn--bcher-kva.no. 300 IN A 10.0.0.2",
+	    "xn--bcher-kva.xn--bcher-kva.no. 300 IN A 10.0.0.3"));
+
+	// A-labels keep their case
+	let mut p = ZoneParser::from_str(zone, "");
+	p.set_idna(true);
+	p.set_case_policy(CasePolicy::Preserve);
+	let name = p.nth(3).unwrap().unwrap().name;
+	assert_eq!(name.to_string(), "XN--BCHER-KVA.xn--bcher-kva.no.");
+
+	// Without the option, UTF-8 is kept as opaque bytes
+	let mut p = ZoneParser::from_str(zone, "");
+	let name = p.nth(1).unwrap().unwrap().name;
+	assert_eq!(name.to_string(), "b\\195\\188cher.example.");
+
+	// Invalid IDNs are errors. Labels are not mapped, so upper case
+	// and compatibility characters are invalid.
+	for bad in ["xn--a.no. 300 IN A 10.0.0.1",
+		    "a\u{3002}b.no. 300 IN A 10.0.0.1",
+		    "-bücher.no. 300 IN A 10.0.0.1",
+		    "Bücher.no. 300 IN A 10.0.0.1",
+		    "\u{FB01}x.no. 300 IN A 10.0.0.1",
+		    // Symbols and emoji, which UTS 46 allows, are not
+		    // PVALID in IDNA2008
+		    "a\u{2665}.example. 300 IN A 10.0.0.1",
+		    "\u{2603}.example. 300 IN A 10.0.0.1",
+		    "xn--n3h.example. 300 IN A 10.0.0.1",
+		    "\u{1F600}.example. 300 IN A 10.0.0.1",
+		    // CONTEXTO rules
+		    "a\u{00B7}b.example. 300 IN A 10.0.0.1",
+		    "\u{0628}\u{0661}\u{06F1}.example. 300 IN A 10.0.0.1"] {
+	    let mut p = ZoneParser::from_str(bad, "");
+	    p.set_idna(true);
+	    let err = p.next().unwrap().unwrap_err();
+	    assert_eq!(err.kind, ErrorKind::BadName, "{}", bad);
+	}
+
+	for (good, ascii) in [("stra\u{00DF}e.", "xn--strae-oqa."),
+			      ("col\u{00B7}legi.", "xn--collegi-xma."),
+			      ("\u{30A2}\u{30FB}\u{30A4}.",
+			       "xn--ccke4x."),
+			      ("\u{0628}\u{0661}.", "xn--ngb8i.")] {
+	    let name: DomainName = good.parse().unwrap();
+	    assert_eq!(name.to_ascii().unwrap().to_string(), ascii);
+	}
+
+	let name: DomainName = "www.xn--bcher-kva.no.".parse().unwrap();
+	assert_eq!(name.to_unicode(), "www.bücher.no.");
+	let name: DomainName = "bücher.no".parse().unwrap();
+	assert_eq!(name.to_ascii().unwrap().to_string(), "xn--bcher-kva.no");
+	let name: DomainName = "xn--a.a\\.b.".parse().unwrap();
+	assert_eq!(name.to_unicode(), "xn--a.a\\.b.");
+	assert_eq!(DomainName::root().to_unicode(), ".");
+    }
+
+    #[test]
+    fn name_validation() {
+	let label = "a".repeat(63);
+	let long = [label.as_str(); 4].join(".");
+	assert!(format!("{}.", &long[..253]).parse::<DomainName>().is_ok());
+	assert_eq!(format!("{}.", &long[..254]).parse::<DomainName>()
+		   .unwrap_err()[..13], *"Name too long");
+	for bad in ["a..b.", ".a", "..", "a\\"] {
+	    assert!(bad.parse::<DomainName>().is_err(), "{}", bad);
+	}
+	assert!(DomainName::root().child(b"").is_err());
+
+	// Too long after the origin is added
+	let zone = format!("{} 300 IN A 10.0.0.1\n\
+			    a..b 300 IN A 10.0.0.2\n\
+			    x 300 IN NS a..b\n", &long[..245]);
+	let mut p = ZoneParser::from_str(&zone, "example.com");
+	p.set_error_recovery(true);
+	let errors: Vec<ParseError> = p.filter_map(|r| r.err()).collect();
+	assert_eq!(errors.iter().map(|e| (e.kind, e.line, e.column))
+		   .collect::<Vec<_>>(), vec!(
+		       (ErrorKind::BadName, 1, 1),
+		       (ErrorKind::BadName, 2, 1),
+		       (ErrorKind::BadName, 3, 13)));
+	let p = ZoneParser::from_str("", "example.com");
+	assert!(p.absolute_name(&long[..245].parse().unwrap()).is_err());
+
+	// Host name warnings
+	let zone = "$ORIGIN example.com.\n\
+		    www 300 IN A 10.0.0.1\n\
+		    *.w 300 IN AAAA ::1\n\
+		    _x 300 IN A 10.0.0.1\n\
+		    -a 300 IN A 10.0.0.1\n\
+		    @ 300 IN MX 10 mail_1\n\
+		    @ 300 IN MX 0 .\n\
+		    @ 300 IN NS ns-.example.net.\n\
+		    _sip._tcp 300 IN SRV 0 0 5060 sip\n\
+		    _443._tcp.www 300 IN TLSA 3 1 1 00\n\
+		    s._domainkey 300 IN TXT \"v=DKIM1\"\n\
+		    _dmarc 300 IN TXT \"v=DMARC1\"\n\
+		    a_b 300 IN TLSA 3 1 1 00\n";
+	let warnings = |checks| {
+	    let mut p = ZoneParser::from_str(zone, "");
+	    p.set_hostname_checks(checks);
+	    let mut warnings = vec!();
+	    while let Some(r) = p.next() {
+		r.unwrap();
+		warnings.extend(p.take_warnings());
+	    }
+	    return warnings;
+	};
+
+	assert!(warnings(false).is_empty());
+	let w = warnings(true);
+	assert!(w.iter().all(|w| w.kind == ErrorKind::BadHostname));
+	assert_eq!(w.iter().map(|w| (w.line, w.token.as_str()))
+		   .collect::<Vec<_>>(), vec!(
+		       (4, "_x.example.com."),
+		       (5, "-a.example.com."),
+		       (6, "mail_1.example.com."),
+		       (8, "ns-.example.net."),
+		       (13, "a_b.example.com.")));
+	assert_eq!(w[0].message,
+		   "Owner _x.example.com. of A record is not a valid host name");
+    }
+
+    #[test]
+    fn wildcards() {
+	let name = |n: &str| n.parse::<DomainName>().unwrap();
+	let wildcard = name("*.example.");
+	assert!(wildcard.is_wildcard());
+	assert!(!name("a.*.example.").is_wildcard());
+	assert!(!name("*a.example.").is_wildcard());
+	assert_eq!(wildcard.wildcard_parent(), Some(name("example.")));
+	assert_eq!(name("www.example.").wildcard_parent(), None);
+	assert_eq!(name("*.").wildcard_parent(), Some(DomainName::root()));
+
+	// The example zone of RFC 4592 section 2.2.1
+	let names: HashSet<DomainName> = [
+	    "example.", "*.example.", "host1.example.", "sub.*.example.",
+	    "_ssh._tcp.host1.example.", "_tcp.host1.example.",
+	    "_ssh._tcp.host2.example.", "_tcp.host2.example.",
+	    "host2.example.", "subdel.example."]
+	    .iter().map(|n| name(n)).collect();
+	let matches = |qname: &str| {
+	    wildcard.wildcard_matches(&name(qname), |n| names.contains(n))
+	};
+
+	assert!(matches("host3.example."));
+	assert!(matches("foo.bar.example."));
+	assert!(!matches("host1.example."));
+	assert!(!matches("sub.*.example."));
+	assert!(!matches("_telnet._tcp.host1.example."));
+	assert!(!matches("host.subdel.example."));
+	assert!(!matches("ghost.*.example."));
+	assert!(!matches("example."));
+	assert!(!matches("www.example.net."));
+	assert!(!name("www.example.").wildcard_matches(
+	    &name("a.www.example."), |_| false));
+    }
+
+    #[test]
+    fn typed_rdata() {
+	let zone = "$ORIGIN example.\n\
+		    $TTL 300\n\
+		    @ SOA ns1 host.master 2024010101 2h 30m 1w 1h\n\
+		    @ NS ns1\n\
+		    @ MX 10 mail\n\
+		    ns1 A 10.0.0.1\n\
+		    ns1 AAAA 2001:db8::1\n\
+		    www CNAME ns1\n\
+		    d DNAME example.net.\n\
+		    1 PTR ns1\n\
+		    @ TXT \"v=spf1 -all\" two\n\
+		    @ SPF \"v=spf1 -all\"\n\
+		    _sip._tcp SRV 0 5 5060 sip\n\
+		    @ HINFO PC Linux\n\
+		    g TYPE999 \\# 2 0aFF\n\
+		    e A \\# 4 0A 000001\n\
+		    q TXT \"#\"\n\
+		    r TXT \"#\" \"x\"\n";
+	let mut p = ZoneParser::from_str(zone, "");
+	let mut rdata = || p.next().unwrap().unwrap().rdata().unwrap();
+	let name = |n: &str| n.parse::<DomainName>().unwrap();
+
+	assert_eq!(rdata(), RData::SOA(Soa {
+	    mname: name("ns1.example."),
+	    rname: name("host.master.example."),
+	    serial: 2024010101,
+	    refresh: 7200,
+	    retry: 1800,
+	    expire: 604800,
+	    minimum: 3600,
+	}));
+	assert_eq!(rdata(), RData::NS(name("ns1.example.")));
+	assert_eq!(rdata(), RData::MX(Mx {
+	    preference: 10,
+	    exchange: name("mail.example."),
+	}));
+	assert_eq!(rdata(), RData::A("10.0.0.1".parse().unwrap()));
+	assert_eq!(rdata(), RData::AAAA("2001:db8::1".parse().unwrap()));
+	assert_eq!(rdata(), RData::CNAME(name("ns1.example.")));
+	assert_eq!(rdata(), RData::DNAME(name("example.net.")));
+	assert_eq!(rdata(), RData::PTR(name("ns1.example.")));
+	assert_eq!(rdata(), RData::TXT(vec!(b"v=spf1 -all".to_vec(),
+					     b"two".to_vec())));
+	assert_eq!(rdata(), RData::SPF(vec!(b"v=spf1 -all".to_vec())));
+	assert_eq!(rdata(), RData::SRV(Srv {
+	    priority: 0,
+	    weight: 5,
+	    port: 5060,
+	    target: name("sip.example."),
+	}));
+	assert_eq!(rdata(), RData::HINFO(Hinfo {
+	    cpu: b"PC".to_vec(),
+	    os: b"Linux".to_vec(),
+	}));
+	assert_eq!(rdata(), RData::Generic(vec!(0x0a, 0xff)));
+	assert_eq!(rdata(), RData::Generic(vec!(10, 0, 0, 1)));
+	// Only an unquoted \# starts generic data
+	assert_eq!(rdata(), RData::TXT(vec!(b"#".to_vec())));
+	assert_eq!(rdata(), RData::TXT(vec!(b"#".to_vec(), b"x".to_vec())));
+
+	assert_rdata_err!("a 1 A 10.0.0.256",
+			  "Bad field 1: Bad IPv4 address 10.0.0.256");
+	let mut p = ZoneParser::from_str("a 1 A 10.0.0.1 10.0.0.2",
+					 "example.");
+	assert_eq!(p.next().unwrap().unwrap().rdata(),
+		   Err(RDataError::FieldCount(RRType::A, 2)));
+	assert_rdata_err!("a 1 MX 65536 mail",
+			  "Bad field 1: Bad 16-bit value 65536");
+	// Numbers have no sign
+	assert_rdata_err!("a 1 MX +10 mail",
+			  "Bad field 1: Bad 16-bit value +10");
+	assert_rdata_err!("a 1 SRV 1 +2 +3 t",
+			  "Bad field 2: Bad 16-bit value +2");
+	assert_rdata_err!("a 1 A \\# +4 0a000001",
+			  "Bad field 2: Bad length +4");
+	let mut p = ZoneParser::from_str("a 1 TYPE+1 \\# 0", "example.");
+	assert_eq!(p.next().unwrap().unwrap_err().kind,
+		   ErrorKind::UnknownType);
+	assert_rdata_err!("a 1 TXT",
+			  "Wrong number of fields (0) for type TXT");
+	assert_rdata_err!(&format!("a 1 TXT {}", "x".repeat(256)),
+			  "Bad field 1: Character-string too long");
+	assert_rdata_err!("a 1 A \\# 4 0a0000",
+			  "Bad field 2: Length 4 does not match the data");
+	assert_rdata_err!("a 1 TYPE999 1 2 3",
+			  "No typed data for type Unknown(999)");
+
+	// The data can be replaced
+	let mut p = ZoneParser::from_str("@ 1 SOA ns1 host 1 2 3 4 5", "ex.");
+	let mut rec = p.next().unwrap().unwrap();
+	if let Ok(RData::SOA(mut soa)) = rec.rdata() {
+	    soa.serial = 2;
+	    rec.set_rdata(&RData::SOA(soa));
+	}
+	assert_eq!(rec.to_string(), "ex. 1 IN SOA ns1.ex. host.ex. 2 2 3 4 5");
+	rec.set_rdata(&RData::Generic(vec!(1, 2)));
+	assert!(rec.generic);
+	assert_eq!(rec.to_string(), "ex. 1 IN SOA \\# 2 0102");
+    }
+
+    #[test]
+    fn dnssec_rdata() {
+	let zone = "$ORIGIN example.\n\
+		    $TTL 300\n\
+		    @ DNSKEY 257 3 ECDSAP256SHA256 (\n\
+		      AwEA\n\
+		      AQID\n\
+		      BA== ) ; KSK\n\
+		    @ CDNSKEY 0 3 0 AA==\n\
+		    @ DS 12345 13 2 (0123456789abcdef\n\
+		      FEDCBA9876543210)\n\
+		    @ CDS 0 0 0 00\n\
+		    @ DLV 1 RSASHA256 1 00ff\n\
+		    @ RRSIG SOA ed25519 1 300 (\n\
+		      20240131235959 1704067200 12345 example.\n\
+		      AAEC )\n\
+		    @ RRSIG TYPE999 8 1 300 4294967295 0 1 . AA==\n\
+		    @ NSEC a NS SOA RRSIG NSEC DNSKEY TYPE1234 CAA\n\
+		    h NSEC3 1 1 0 - (\n\
+		      0p9mhaveqvm6t7vbl5lop2u3t2rp3tom A RRSIG )\n\
+		    @ NSEC3PARAM 1 0 10 aabbccdd\n";
+	let mut p = ZoneParser::from_str(zone, "");
+	let mut next = || p.next().unwrap().unwrap();
+
+	let mut rec = next();
+	assert_eq!(rec.data.len(), 6);
+	assert_eq!(rec.rdata(), Ok(RData::DNSKEY(Dnskey {
+	    flags: 257,
+	    protocol: 3,
+	    algorithm: 13,
+	    public_key: vec!(3, 1, 0, 1, 2, 3, 4),
+	})));
+	let rdata = rec.rdata().unwrap();
+	rec.set_rdata(&rdata);
+	assert_eq!(rec.to_string(),
+		   "example. 300 IN DNSKEY 257 3 13 AwEAAQIDBA==");
+
+	assert!(matches!(next().rdata(), Ok(RData::CDNSKEY(_))));
+	assert_eq!(next().rdata(), Ok(RData::DS(Ds {
+	    key_tag: 12345,
+	    algorithm: 13,
+	    digest_type: 2,
+	    digest: vec!(0x01, 0x23, 0x45, 0x67, 0x89, 0xab, 0xcd, 0xef,
+			 0xfe, 0xdc, 0xba, 0x98, 0x76, 0x54, 0x32, 0x10),
+	})));
+	assert!(matches!(next().rdata(), Ok(RData::CDS(_))));
+	assert!(matches!(next().rdata(),
+			 Ok(RData::DLV(Ds { algorithm: 8, .. }))));
+
+	let mut rec = next();
+	let rdata = rec.rdata().unwrap();
+	assert_eq!(rdata, RData::RRSIG(Rrsig {
+	    type_covered: RRType::SOA,
+	    algorithm: 15,
+	    labels: 1,
+	    original_ttl: 300,
+	    expiration: 1706745599,
+	    inception: 1704067200,
+	    key_tag: 12345,
+	    signer: "example.".parse().unwrap(),
+	    signature: vec!(0, 1, 2),
+	}));
+	rec.set_rdata(&rdata);
+	assert_eq!(rec.to_string(),
+		   "example. 300 IN RRSIG SOA 15 1 300 20240131235959 \
+		    20240101000000 12345 example. AAEC");
+
+	match next().rdata() {
+	    Ok(RData::RRSIG(sig)) => {
+		assert_eq!(sig.type_covered, RRType::Unknown(999));
+		assert_eq!((sig.expiration, sig.inception), (u32::MAX, 0));
+	    },
+	    other => panic!("Expected RRSIG, got {:?}", other),
+	}
+
+	let mut rec = next();
+	let rdata = rec.rdata().unwrap();
+	match &rdata {
+	    RData::NSEC(nsec) => {
+		assert_eq!(nsec.next, "a.example.");
+		assert_eq!(nsec.types.types(), vec!(
+		    RRType::NS, RRType::SOA, RRType::RRSIG, RRType::NSEC,
+		    RRType::DNSKEY, RRType::CAA, RRType::Unknown(1234)));
+		assert!(nsec.types.contains(RRType::NS));
+		assert!(!nsec.types.contains(RRType::A));
+		assert_eq!(nsec.types.to_wire(), vec!(
+		    0, 7, 0x22, 0, 0, 0, 0, 3, 0x80,
+		    1, 1, 0x40,
+		    4, 27, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
+		    0, 0, 0, 0, 0, 0, 0, 0, 0x20));
+	    },
+	    other => panic!("Expected NSEC, got {:?}", other),
+	}
+	rec.set_rdata(&rdata);
+	assert_eq!(rec.to_string(), "example. 300 IN NSEC a.example. \
+				     NS SOA RRSIG NSEC DNSKEY CAA TYPE1234");
+
+	let mut rec = next();
+	let rdata = rec.rdata().unwrap();
+	match &rdata {
+	    RData::NSEC3(nsec3) => {
+		assert_eq!((nsec3.hash_algorithm, nsec3.flags, nsec3.iterations),
+			   (1, 1, 0));
+		assert!(nsec3.salt.is_empty());
+		assert_eq!(nsec3.next_hashed.len(), 20);
+		assert_eq!(nsec3.types.types(), vec!(RRType::A, RRType::RRSIG));
+	    },
+	    other => panic!("Expected NSEC3, got {:?}", other),
+	}
+	rec.set_rdata(&rdata);
+	assert_eq!(rec.to_string(), "h.example. 300 IN NSEC3 1 1 0 - \
+				     0P9MHAVEQVM6T7VBL5LOP2U3T2RP3TOM A RRSIG");
+
+	assert_eq!(next().rdata(), Ok(RData::NSEC3PARAM(Nsec3Param {
+	    hash_algorithm: 1,
+	    flags: 0,
+	    iterations: 10,
+	    salt: vec!(0xaa, 0xbb, 0xcc, 0xdd),
+	})));
+
+	assert_rdata_err!("a 1 DNSKEY 257 3 FOO AA==",
+			  "Bad field 3: Bad algorithm FOO");
+	assert_rdata_err!("a 1 DNSKEY 257 3 8 A",
+			  "Bad field 4: Bad base64 data A");
+	assert_rdata_err!("a 1 DNSKEY 257 3 8",
+			  "Wrong number of fields (3) for type DNSKEY");
+	assert_rdata_err!("a 1 DS 1 8 2 0g", "Bad field 4: Bad hex data 0g");
+	assert_rdata_err!(
+	    &format!("a 1 DS 1 8 1 {}", "+f".repeat(20)),
+	    format!("Bad field 4: Bad hex data {}", "+f".repeat(20)));
+	assert_rdata_err!("a 1 NSEC3PARAM 1 0 10 +a",
+			  "Bad field 4: Bad salt +a");
+	assert_rdata_err!("a 1 DS 1 +8 2 00", "Bad field 2: Bad algorithm +8");
+	assert_rdata_err!("a 1 RRSIG A 8 1 1 +1 0 1 . AA==",
+			  "Bad field 5: Bad time +1");
+	assert_rdata_err!("a 1 NSEC b TYPE+1",
+			  "Bad field 2: Unknown type TYPE+1");
+	assert_rdata_err!("a 1 A \\# 2 +a+b",
+			  "Bad field 3: Bad hex data +a+b");
+	assert_rdata_err!("a 1 RRSIG A 8 1 1 20240230000000 0 1 . AA==",
+			  "Bad field 5: Bad time 20240230000000");
+	assert_rdata_err!("a 1 RRSIG FOO 8 1 1 0 0 1 . AA==",
+			  "Bad field 1: Unknown type FOO");
+	assert_rdata_err!("a 1 NSEC b FOO", "Bad field 2: Unknown type FOO");
+	assert_rdata_err!(
+	    "a 1 NSEC3 1 0 0 - 0p9mhaveqvm6t7vbl5lop2u3t2rp3toz",
+	    "Bad field 5: Bad base32 data 0p9mhaveqvm6t7vbl5lop2u3t2rp3toz");
+    }
+
+    #[test]
+    fn security_rdata() {
+	let zone = "$ORIGIN example.\n\
+		    $TTL 300\n\
+		    @ CAA 0 issue \"letsencrypt.org\"\n\
+		    @ CAA 128 issuewild \"ca.example.net; account=230123; \
+		      policy=ev\"\n\
+		    @ CAA 0 issue \";\"\n\
+		    @ CAA 0 iodef \"mailto:security@example.com\"\n\
+		    @ CAA 0 tbs \"Unknown\"\n\
+		    _443._tcp.www TLSA 3 1 1 (\n\
+		      %SHA256% )\n\
+		    x._smimecert SMIMEA 3 0 0 308203\n\
+		    @ SSHFP 4 2 %SHA256%\n\
+		    @ OPENPGPKEY AQID BA==\n\
+		    @ CERT pgp 0 0 AAEC\n\
+		    @ CERT 65280 1 RSASHA256 AA==\n";
+	let sha1_hex = "0123456789abcdef0123456789abcdef01234567";
+	let sha256_hex = "0123456789abcdef".repeat(4);
+	let sha256 = [0x01, 0x23, 0x45, 0x67, 0x89, 0xab, 0xcd, 0xef].repeat(4);
+	let zone = zone.replace("%SHA256%", &sha256_hex);
+	let mut p = ZoneParser::from_str(&zone, "");
+	let mut next = || p.next().unwrap().unwrap();
+
+	let mut rec = next();
+	let rdata = rec.rdata().unwrap();
+	assert_eq!(rdata, RData::CAA(Caa {
+	    flags: 0,
+	    tag: "issue".to_string(),
+	    value: b"letsencrypt.org".to_vec(),
+	}));
+	rec.set_rdata(&rdata);
+	assert_eq!(rec.to_string(),
+		   "example. 300 IN CAA 0 issue letsencrypt.org");
+
+	let mut rec = next();
+	let rdata = rec.rdata().unwrap();
+	rec.set_rdata(&rdata);
+	assert_eq!(rec.to_string(), "example. 300 IN CAA 128 issuewild \
+				     \"ca.example.net; account=230123; \
+				     policy=ev\"");
+	assert!(matches!(next().rdata(), Ok(RData::CAA(_))));
+	assert!(matches!(next().rdata(), Ok(RData::CAA(_))));
+	assert!(matches!(next().rdata(), Ok(RData::CAA(_))));
+
+	let mut rec = next();
+	let rdata = rec.rdata().unwrap();
+	assert_eq!(rdata, RData::TLSA(Tlsa {
+	    usage: 3,
+	    selector: 1,
+	    matching_type: 1,
+	    data: sha256.clone(),
+	}));
+	rec.set_rdata(&rdata);
+	assert_eq!(rec.to_string(), format!(
+	    "_443._tcp.www.example. 300 IN TLSA 3 1 1 {}",
+	    "0123456789ABCDEF".repeat(4)));
+
+	assert_eq!(next().rdata(), Ok(RData::SMIMEA(Tlsa {
+	    usage: 3,
+	    selector: 0,
+	    matching_type: 0,
+	    data: vec!(0x30, 0x82, 0x03),
+	})));
+	assert_eq!(next().rdata(), Ok(RData::SSHFP(Sshfp {
+	    algorithm: 4,
+	    fingerprint_type: 2,
+	    fingerprint: sha256,
+	})));
+	assert_eq!(next().rdata(), Ok(RData::OPENPGPKEY(vec!(1, 2, 3, 4))));
+
+	let mut rec = next();
+	let rdata = rec.rdata().unwrap();
+	assert_eq!(rdata, RData::CERT(Cert {
+	    cert_type: 3,
+	    key_tag: 0,
+	    algorithm: 0,
+	    certificate: vec!(0, 1, 2),
+	}));
+	rec.set_rdata(&rdata);
+	assert_eq!(rec.to_string(), "example. 300 IN CERT PGP 0 0 AAEC");
+	let mut rec = next();
+	let rdata = rec.rdata().unwrap();
+	rec.set_rdata(&rdata);
+	assert_eq!(rec.to_string(), "example. 300 IN CERT 65280 1 8 AA==");
+
+	assert_rdata_err!("a 1 CAA 0 issue ca..example",
+			  "Bad field 3: Bad issuer ca..example");
+	assert_rdata_err!("a 1 CAA 0 issue \"ca; account\"",
+			  "Bad field 3: Bad parameter account");
+	assert_rdata_err!("a 1 CAA 0 issuewild \"ca; a=b;; c=d\"",
+			  "Bad field 3: Empty parameter");
+	assert_rdata_err!("a 1 CAA 0 iodef ftp://example.com/",
+			  "Bad field 3: Bad iodef URL ftp://example.com/");
+	assert_rdata_err!("a 1 CAA 0 is-sue x", "Bad field 2: Bad tag is-sue");
+	assert_rdata_err!("a 1 CAA 256 issue x",
+			  "Bad field 1: Bad 8-bit value 256");
+	// SHA-1 digests where SHA-256 is expected, and the other way round
+	assert_rdata_err!(
+	    &format!("a 1 TLSA 3 1 1 {}", sha1_hex),
+	    "Bad field 4: Digest length 20 does not match type 1 (32)");
+	assert_rdata_err!(
+	    &format!("a 1 SSHFP 1 1 {}", sha256_hex),
+	    "Bad field 3: Digest length 32 does not match type 1 (20)");
+	assert_rdata_err!(
+	    "a 1 SMIMEA 3 0 2 00",
+	    "Bad field 4: Digest length 1 does not match type 2 (64)");
+	assert_rdata_err!("a 1 CERT FOO 0 0 AA==",
+			  "Bad field 1: Bad certificate type FOO");
+	assert_rdata_err!("a 1 OPENPGPKEY A",
+			  "Bad field 1: Bad base64 data A");
+    }
+
+    #[test]
+    fn other_rdata() {
+	let zone = "$ORIGIN example.\n\
+		    $TTL 300\n\
+		    @ RP admin txt.example.\n\
+		    @ AFSDB 1 afs\n\
+		    @ NAPTR 100 10 \"S\" \"SIP+D2U\" \"\" _sip._udp\n\
+		    @ NAPTR 100 50 \"u\" \"E2U+sip\" \
+		      \"!^.*$!sip:info@example.com!\" .\n\
+		    @ KX 10 kx\n\
+		    @ APL 1:192.168.32.0/21 !1:192.168.38.0/28 \
+		      2:2001:db8::/32\n\
+		    @ IPSECKEY 10 0 2 . AQNRU3mG7TVTO2BkR47usntb102uFJtu \
+		      gbo6BSGvgqt4AQ==\n\
+		    @ IPSECKEY 10 2 2 2001:0DB8:0:8002::2000:1 AQID\n\
+		    @ IPSECKEY 10 3 0 gw\n\
+		    @ DHCID AAIBY2/AuCccgoJbsaxcQc9TUapptP69lOjxfNuVAA2kjEA=\n\
+		    www HIP 2 200100107B1A74DF365639CC39F1D578 AwEAAQ== \
+		      rvs1 rvs2.example.com.\n\
+		    @ CSYNC 66 3 A NS AAAA\n\
+		    @ ZONEMD 2018031900 1 1 %SHA384%\n\
+		    host EUI48 00-00-5E-00-53-2a\n\
+		    host EUI64 00-00-5e-ef-10-00-00-2a\n\
+		    @ TKEY hmac-sha256. 20240101000000 1704153600 3 BADKEY \
+		      4 AAECAw== 0\n\
+		    @ TSIG hmac-sha256. 1704067200 300 3 AAEC 1234 0 0\n\
+		    @ URI 10 1 \"ftp://ftp1.example.com/public\"\n\
+		    @ WALLET \"BTC\" \"bc1qar0srrr7xfkvy5l643lydnw9re59gtzzwf\"\n\
+		    @ TA 12345 8 1 0123456789abcdef01234567\n\
+		    @ KEY 256 3 8 AwEAAQ==\n\
+		    @ SIG A 8 1 300 20240101000000 20231201000000 1 \
+		      example. AAEC\n";
+	let zone = zone.replace("%SHA384%", &"0123456789abcdef".repeat(6));
+	let mut p = ZoneParser::from_str(&zone, "");
+	let mut next = || p.next().unwrap().unwrap();
+	// The data in presentation format, after a round trip
+	let round_trip = |mut rec: Record| {
+	    let rdata = rec.rdata().unwrap();
+	    rec.set_rdata(&rdata);
+	    let data = rec.data.iter().map(|d| d.to_string())
+		.collect::<Vec<_>>();
+	    assert_eq!(rec.rdata(), Ok(rdata));
+	    return data.join(" ");
+	};
+
+	assert_eq!(round_trip(next()), "admin.example. txt.example.");
+	assert_eq!(round_trip(next()), "1 afs.example.");
+	assert_eq!(round_trip(next()),
+		   "100 10 S SIP+D2U \"\" _sip._udp.example.");
+	assert_eq!(round_trip(next()),
+		   "100 50 u E2U+sip !^.*$!sip:info@example.com! .");
+	assert_eq!(round_trip(next()), "10 kx.example.");
+
+	let rec = next();
+	assert_eq!(rec.rdata(), Ok(RData::APL(vec!(
+	    AplItem {
+		negation: false,
+		address: "192.168.32.0".parse().unwrap(),
+		prefix: 21,
+	    },
+	    AplItem {
+		negation: true,
+		address: "192.168.38.0".parse().unwrap(),
+		prefix: 28,
+	    },
+	    AplItem {
+		negation: false,
+		address: "2001:db8::".parse().unwrap(),
+		prefix: 32,
+	    }))));
+	assert_eq!(round_trip(rec), "1:192.168.32.0/21 !1:192.168.38.0/28 \
+				     2:2001:db8::/32");
+
+	assert_eq!(round_trip(next()), "10 0 2 . \
+		   AQNRU3mG7TVTO2BkR47usntb102uFJtugbo6BSGvgqt4AQ==");
+	assert_eq!(round_trip(next()), "10 2 2 2001:db8:0:8002::2000:1 AQID");
+	let rec = next();
+	match rec.rdata() {
+	    Ok(RData::IPSECKEY(key)) => {
+		assert_eq!(key.gateway, IpseckeyGateway::Name(
+		    "gw.example.".parse().unwrap()));
+		assert!(key.public_key.is_empty());
+	    },
+	    other => panic!("Expected IPSECKEY, got {:?}", other),
+	}
+	assert_eq!(round_trip(rec), "10 3 0 gw.example.");
+
+	assert!(matches!(next().rdata(), Ok(RData::DHCID(d)) if d.len() == 35));
+	assert_eq!(round_trip(next()), "2 200100107B1A74DF365639CC39F1D578 \
+				        AwEAAQ== rvs1.example. rvs2.example.com.");
+	assert_eq!(round_trip(next()), "66 3 A NS AAAA");
+	assert!(matches!(next().rdata(),
+			 Ok(RData::ZONEMD(z)) if z.digest.len() == 48));
+	let rec = next();
+	assert_eq!(rec.rdata(), Ok(RData::EUI48([0, 0, 0x5e, 0, 0x53, 0x2a])));
+	assert_eq!(round_trip(rec), "00-00-5e-00-53-2a");
+	let rec = next();
+	#[allow(deprecated)]
+	let old_name = RRType::EUI164;
+	assert_eq!(rec.rrtype, old_name);
+	assert_eq!(round_trip(rec), "00-00-5e-ef-10-00-00-2a");
+	assert_eq!(round_trip(next()), "hmac-sha256. 20240101000000 \
+				        20240102000000 3 BADKEY 4 AAECAw== 0");
+
+	let rec = next();
+	assert_eq!(rec.rdata(), Ok(RData::TSIG(Tsig {
+	    algorithm: "hmac-sha256.".parse().unwrap(),
+	    time_signed: 1704067200,
+	    fudge: 300,
+	    mac: vec!(0, 1, 2),
+	    original_id: 1234,
+	    error: 0,
+	    other: vec!(),
+	})));
+	assert_eq!(round_trip(rec),
+		   "hmac-sha256. 1704067200 300 3 AAEC 1234 NOERROR 0");
+
+	assert_eq!(round_trip(next()), "10 1 ftp://ftp1.example.com/public");
+	assert_eq!(round_trip(next()),
+		   "BTC bc1qar0srrr7xfkvy5l643lydnw9re59gtzzwf");
+	assert!(matches!(next().rdata(), Ok(RData::TA(_))));
+	assert!(matches!(next().rdata(), Ok(RData::KEY(_))));
+	assert!(matches!(next().rdata(), Ok(RData::SIG(_))));
+
+	assert_rdata_err!("a 1 NAPTR 1 1 \"S+\" \"\" \"\" .",
+			  "Bad field 3: Bad flags S+");
+	assert_rdata_err!("a 1 APL 3:1.2.3.4/8",
+			  "Bad field 1: Bad address family in 3:1.2.3.4/8");
+	assert_rdata_err!("a 1 APL 1:192.168.0.0/8 1:192.168.0.0/33",
+			  "Bad field 2: Bad prefix in 1:192.168.0.0/33");
+	assert_rdata_err!("a 1 APL 1:192.168.0.0/+8",
+			  "Bad field 1: Bad prefix in 1:192.168.0.0/+8");
+	assert_rdata_err!("a 1 TSIG alg. +1 300 0 1 0 0",
+			  "Bad field 2: Bad 48-bit value +1");
+	assert_rdata_err!("a 1 APL 2:192.168.0.0/8",
+			  "Bad field 1: Bad address in 2:192.168.0.0/8");
+	assert_rdata_err!("a 1 IPSECKEY 10 4 2 . AA==",
+			  "Bad field 2: Bad gateway type 4");
+	assert_rdata_err!("a 1 IPSECKEY 10 0 2 gw AA==",
+			  "Bad field 4: Gateway must be . for type 0");
+	assert_rdata_err!("a 1 IPSECKEY 10 1 2 ::1 AA==",
+			  "Bad field 4: Bad IPv4 address ::1");
+	assert_rdata_err!("a 1 DHCID AAEC",
+			  "Bad field 1: DHCID data too short");
+	assert_rdata_err!("a 1 HIP 2 0g AA== rvs",
+			  "Bad field 2: Bad hex data 0g");
+	assert_rdata_err!("a 1 CSYNC 1 3 FOO",
+			  "Bad field 3: Unknown type FOO");
+	assert_rdata_err!(
+	    "a 1 ZONEMD 1 1 1 00ff",
+	    "Bad field 4: Digest length 2 does not match type 1 (48)");
+	assert_rdata_err!("a 1 ZONEMD 1 1 240 00ff",
+			  "Bad field 4: Digest length 2 is too short");
+	assert_rdata_err!("a 1 EUI48 00-00-5e-00-53-+a",
+			  "Bad field 1: Bad address 00-00-5e-00-53-+a");
+	assert_rdata_err!("a 1 EUI48 00-00-5e-00-53",
+			  "Bad field 1: Bad address 00-00-5e-00-53");
+	assert_rdata_err!("a 1 EUI64 00-00-5e-00-53-2a-0-01",
+			  "Bad field 1: Bad address 00-00-5e-00-53-2a-0-01");
+	assert_rdata_err!("a 1 TKEY alg. 0 0 3 BADERR 0 0",
+			  "Bad field 5: Bad error BADERR");
+	assert_rdata_err!("a 1 TSIG alg. 281474976710656 300 0 1 0 0",
+			  "Bad field 2: Bad 48-bit value 281474976710656");
+	assert_rdata_err!("a 1 TSIG alg. 1 300 4 AAEC 1 0 0",
+			  "Bad field 5: Length 3 does not match size 4");
+	assert_rdata_err!("a 1 TSIG alg. 1 300 0 1 0 0 AA==",
+			  "Wrong number of fields (8) for type TSIG");
+	assert_rdata_err!("a 1 URI 1 1 \"\"", "Bad field 3: Empty target");
+	assert_rdata_err!("a 1 RP admin",
+			  "Wrong number of fields (1) for type RP");
+    }
+
+    #[test]
+    fn loc_rdata() {
+	// Examples from RFC 1876
+	let zone = "$ORIGIN kei.com.\n\
+		    $TTL 300\n\
+		    cambridge-net LOC 42 21 54 N 71 06 18 W -24m 30m\n\
+		    loiosh LOC 42 21 43.952 N 71 5 6.344 W -24m 1m 200m\n\
+		    pipex.net. LOC 52 14 05 N 00 08 50 E 10m\n\
+		    curtin.edu.au. LOC 32 7 19 S 116 2 25 E 10\n\
+		    null LOC 0 n 0 e 0 1.5 0.01m 90000000.00m\n";
+	let mut p = ZoneParser::from_str(zone, "");
+	let mut next = || p.next().unwrap().unwrap();
+
+	let mut rec = next();
+	let rdata = rec.rdata().unwrap();
+	let loc = Loc {
+	    size: 0x33,
+	    horiz_pre: 0x16,
+	    vert_pre: 0x13,
+	    latitude: (1 << 31) + 152514000,
+	    longitude: (1 << 31) - 255978000,
+	    altitude: 10000000 - 2400,
+	};
+	assert_eq!(rdata, RData::LOC(loc.clone()));
+	assert_eq!(loc.to_wire(), vec!(
+	    0, 0x33, 0x16, 0x13, 0x89, 0x17, 0x2d, 0xd0,
+	    0x70, 0xbe, 0x15, 0xf0, 0x00, 0x98, 0x8d, 0x20));
+	assert_eq!(Loc::from_wire(&loc.to_wire()), Some(loc.clone()));
+	assert_eq!(loc.altitude_meters(), -24.0);
+	assert_eq!(loc.size_meters(), 30.0);
+	assert_eq!(loc.horiz_pre_meters(), 10000.0);
+	assert_eq!(loc.vert_pre_meters(), 10.0);
+	rec.set_rdata(&rdata);
+	assert_eq!(rec.to_string(), "cambridge-net.kei.com. 300 IN LOC \
+				     42 21 54.000 N 71 6 18.000 W \
+				     -24m 30m 10000m 10m");
+
+	let mut rec = next();
+	let rdata = rec.rdata().unwrap();
+	rec.set_rdata(&rdata);
+	assert_eq!(rec.to_string(), "loiosh.kei.com. 300 IN LOC \
+				     42 21 43.952 N 71 5 6.344 W \
+				     -24m 1m 200m 10m");
+
+	let loc = match next().rdata() {
+	    Ok(RData::LOC(loc)) => loc,
+	    other => panic!("Expected LOC, got {:?}", other),
+	};
+	assert!((loc.latitude_degrees() - 52.234722).abs() < 1e-6);
+	assert!((loc.longitude_degrees() - 0.147222).abs() < 1e-6);
+	assert_eq!(Loc::from_degrees(loc.latitude_degrees(),
+				     loc.longitude_degrees(), 10.0),
+		   Some(loc));
+
+	let loc = match next().rdata() {
+	    Ok(RData::LOC(loc)) => loc,
+	    other => panic!("Expected LOC, got {:?}", other),
+	};
+	assert!((loc.latitude_degrees() + 32.121944).abs() < 1e-6);
+	assert!((loc.longitude_degrees() - 116.040278).abs() < 1e-6);
+
+	// Sizes are rounded down to one digit
+	let mut rec = next();
+	let rdata = rec.rdata().unwrap();
+	rec.set_rdata(&rdata);
+	assert_eq!(rec.to_string(), "null.kei.com. 300 IN LOC \
+				     0 0 0.000 N 0 0 0.000 E \
+				     0m 1m 0.01m 90000000m");
+
+	assert_eq!(Loc::from_degrees(90.5, 0.0, 0.0), None);
+	assert_eq!(Loc::from_degrees(0.0, 0.0, -100000.01), None);
+	assert_eq!(Loc::from_wire(&[0; 15]), None);
+	let mut wire = vec!(0, 0x1a, 0x16, 0x13);
+	wire.extend_from_slice(&[0x80, 0, 0, 0, 0x80, 0, 0, 0, 0, 0, 0, 0]);
+	assert_eq!(Loc::from_wire(&wire), None);
+
+	assert_rdata_err!("a 1 LOC 90 0 0.001 N 0 E 0",
+			  "Bad field 1: 90 0 0.001 N out of range");
+	assert_rdata_err!("a 1 LOC 42 60 N 0 E 0",
+			  "Bad field 2: Bad minutes 60");
+	assert_rdata_err!("a 1 LOC 42 +1 N 0 E 0",
+			  "Bad field 2: Bad minutes +1");
+	assert_rdata_err!("a 1 LOC +42 N 0 E 0",
+			  "Bad field 1: Bad degrees +42");
+	assert_rdata_err!("a 1 LOC 42 N 0 E +1",
+			  "Bad field 5: Bad altitude +1");
+	assert_rdata_err!("a 1 LOC 42 1 60 N 0 E 0",
+			  "Bad field 3: Bad seconds 60");
+	assert_rdata_err!("a 1 LOC 42 1 1.0001 N 0 E 0",
+			  "Bad field 3: Bad seconds 1.0001");
+	assert_rdata_err!("a 1 LOC 42 21 54 X 71 W 0",
+			  "Bad field 4: Expected N or S");
+	assert_rdata_err!("a 1 LOC 42 N 181 W 0",
+			  "Bad field 3: Bad degrees 181");
+	assert_rdata_err!("a 1 LOC 42 N W 0 0",
+			  "Bad field 3: Missing degrees");
+	assert_rdata_err!("a 1 LOC 42 N 71 W 42849673m",
+			  "Bad field 5: Bad altitude 42849673m");
+	assert_rdata_err!("a 1 LOC 42 N 71 W 0 -1m",
+			  "Bad field 6: Bad size -1m");
+	assert_rdata_err!("a 1 LOC 42 N 71 W 0 1 1 1 1",
+			  "Wrong number of fields (9) for type LOC");
+	assert_rdata_err!("a 1 LOC 42 N 71 W",
+			  "Wrong number of fields (4) for type LOC");
+    }
+
+    #[test]
+    fn svcb_rdata() {
+	let zone = "$ORIGIN example.\n\
+		    $TTL 300\n\
+		    @ HTTPS 0 svc\n\
+		    svc HTTPS 1 . alpn=\"h2,h3\" port=8443 ech=AAEC (\n\
+		      ipv4hint=192.0.2.1,192.0.2.2 mandatory=alpn,port )\n\
+		    _dns.ns SVCB 1 ns dohpath=/q{?dns} no-default-alpn \
+		      alpn=dot ipv6hint=\"2001:db8::1\" key65000=\"a b\" \
+		      key65001\n\
+		    x HTTPS 1 . alpn=\"f\\\\\\\\oo\\\\,bar,h2\"\n\
+		    y HTTPS 1 . alpn=f\\\\\\092oo\\092,bar,h2\n";
+	let mut p = ZoneParser::from_str(zone, "");
+	let mut next = || p.next().unwrap().unwrap();
+
+	assert_eq!(next().rdata(), Ok(RData::HTTPS(Svcb {
+	    priority: 0,
+	    target: "svc.example.".parse().unwrap(),
+	    params: vec!(),
+	})));
+
+	let mut rec = next();
+	let rdata = rec.rdata().unwrap();
+	assert_eq!(rdata, RData::HTTPS(Svcb {
+	    priority: 1,
+	    target: ".".parse().unwrap(),
+	    params: vec!(
+		SvcParam::Mandatory(vec!(1, 3)),
+		SvcParam::Alpn(vec!(b"h2".to_vec(), b"h3".to_vec())),
+		SvcParam::Port(8443),
+		SvcParam::Ipv4Hint(vec!("192.0.2.1".parse().unwrap(),
+					"192.0.2.2".parse().unwrap())),
+		SvcParam::Ech(vec!(0, 1, 2))),
+	}));
+	rec.set_rdata(&rdata);
+	assert_eq!(rec.to_string(), "svc.example. 300 IN HTTPS 1 . \
+				     mandatory=alpn,port alpn=h2,h3 port=8443 \
+				     ipv4hint=192.0.2.1,192.0.2.2 ech=AAEC");
+
+	let mut rec = next();
+	let rdata = rec.rdata().unwrap();
+	match &rdata {
+	    RData::SVCB(svcb) => {
+		assert_eq!(svcb.target.to_string(), "ns.example.");
+		assert_eq!(svcb.params.iter().map(|p| p.key())
+			   .collect::<Vec<_>>(), vec!(1, 2, 6, 7, 65000, 65001));
+		assert_eq!(svcb.params[4],
+			   SvcParam::Key(65000, b"a b".to_vec()));
+		assert_eq!(svcb.params[5], SvcParam::Key(65001, vec!()));
+	    },
+	    other => panic!("Expected SVCB, got {:?}", other),
+	}
+	rec.set_rdata(&rdata);
+	assert_eq!(rec.to_string(), "_dns.ns.example. 300 IN SVCB 1 \
+				     ns.example. alpn=dot no-default-alpn \
+				     ipv6hint=2001:db8::1 dohpath=/q{?dns} \
+				     \"key65000=a b\" key65001");
+
+	// Escaped commas and backslashes in a value list
+	for _ in 0..2 {
+	    let mut rec = next();
+	    let rdata = rec.rdata().unwrap();
+	    match &rdata {
+		RData::HTTPS(Svcb { params, .. }) => {
+		    assert_eq!(params, &vec!(SvcParam::Alpn(vec!(
+			b"f\\oo,bar".to_vec(), b"h2".to_vec()))));
+		},
+		other => panic!("Expected HTTPS, got {:?}", other),
+	    }
+	    rec.set_rdata(&rdata);
+	    assert!(rec.to_string().ends_with(
+		" alpn=f\\\\\\\\oo\\\\,bar,h2"));
+	}
+
+	assert_rdata_err!("a 1 HTTPS 0 . alpn=h2",
+			  "Bad field 3: Parameters in AliasMode");
+	assert_rdata_err!("a 1 HTTPS 1 . mandatory=port alpn=h2",
+			  "Bad field 3: Mandatory key port missing");
+	assert_rdata_err!("a 1 HTTPS 1 . mandatory=mandatory",
+			  "Bad field 3: Mandatory lists mandatory");
+	assert_rdata_err!("a 1 HTTPS 1 . mandatory=alpn,key1 alpn=h2",
+			  "Bad field 3: Mandatory lists alpn twice");
+	assert_rdata_err!("a 1 HTTPS 1 . no-default-alpn",
+			  "Bad field 3: no-default-alpn without alpn");
+	assert_rdata_err!("a 1 HTTPS 1 . port=1 port=2",
+			  "Bad field 4: Duplicate key port");
+	assert_rdata_err!("a 1 HTTPS 1 . alpn=h2,,h3",
+			  "Bad field 3: Bad value list for alpn");
+	assert_rdata_err!("a 1 HTTPS 1 . foo=1", "Bad field 3: Bad key foo");
+	assert_rdata_err!("a 1 HTTPS 1 . port=+443",
+			  "Bad field 3: Bad port +443");
+	assert_rdata_err!("a 1 HTTPS 1 . key+1=a",
+			  "Bad field 3: Bad key key+1");
+	assert_rdata_err!("a 1 HTTPS 1 . key65535",
+			  "Bad field 3: Bad key key65535");
+	assert_rdata_err!("a 1 HTTPS 1 . ipv4hint=::1",
+			  "Bad field 3: Bad address ::1");
+	assert_rdata_err!("a 1 HTTPS 1 . port",
+			  "Bad field 3: Missing value for port");
+	assert_rdata_err!("a 1 HTTPS 1",
+			  "Wrong number of fields (1) for type HTTPS");
+
+	let mut p = ZoneParser::from_str("a 1 HTTPS 1 . alpn=\"h2\n",
+					 "example.");
+	assert_eq!(p.next().unwrap().unwrap_err().kind, ErrorKind::BadQuoting);
+	let mut p = ZoneParser::from_str("a 1 TXT a=\"b\"\n", "example.");
+	assert_eq!(p.next().unwrap().unwrap_err().kind, ErrorKind::BadQuoting);
+    }
+
+    #[test]
+    fn tokenizer() {
+	let zone = "\u{feff}$ORIGIN example.\r\n\
+		    a\\ b 300 IN TXT \"(\" \")x(\" \"a;b\" c\\;d ; comment\r\n\
+		    c 300 IN TXT ( \"one\r\n\
+		    two ) ;\" )\r\n\
+		    \t300 IN A 10.0.0.1\r\n\
+		    d 300 IN TXT \"a\"(\"b\")\r\n";
+	let mut p = ZoneParser::from_str(zone, "");
+	assert_next_rec!(
+	    p, "a\\ b.example.", 300, RRClass::IN, RRType::TXT,
+	    "(", ")x(", "a;b", "c;d");
+	assert_next_rec!(
+	    p, "c.example.", 300, RRClass::IN, RRType::TXT, "one\ntwo ) ;");
+	assert_next_rec!(
+	    p, "c.example.", 300, RRClass::IN, RRType::A, "10.0.0.1");
+	assert_next_rec!(
+	    p, "d.example.", 300, RRClass::IN, RRType::TXT, "a", "b");
+	assert!(p.next().is_none());
+
+	// Owner names, TTLs and types can't be quoted
+	let mut p = ZoneParser::from_str("\"a\" 300 IN A 10.0.0.1\n", "x.");
+	assert_next_err!(
+	    p, ErrorKind::BadQuoting, "Unexpected quoted string on line 1 column 1");
+
+	// An escape can't continue on the next line
+	let mut p = ZoneParser::from_str("a 300 IN TXT ( x\\\n y )\n", "x.");
+	assert_next_err!(
+	    p, ErrorKind::BadEscaping, "Bad escaping on line 1 column 16");
     }
 }